@@ -4,7 +4,7 @@
 // MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option. This file may not be copied,
 // modified, or distributed except according to those terms.
 
-use mode::{Automaton, Family, Mode};
+use mode::{AnyMode, Automaton, Family, Mode};
 
 // This meta-struct represents a group of all Modes that can be used with the same Automaton, i.e. all states in the
 // same state machine. By implementing Family, we can specify the common interface that will be exposed for all states
@@ -21,9 +21,10 @@ impl Family for ActivityFamily {
     type Mode = Box<dyn Activity>;
 }
 
-// This trait defines a common interface for all Modes in ActivityFamily.
+// This trait defines a common interface for all Modes in ActivityFamily. AnyMode allows the current Mode to be
+// downcast to its concrete type from outside the Automaton, so that data specific to one Mode can be accessed.
 //
-trait Activity : Mode<Family = ActivityFamily> {
+trait Activity : Mode<Family = ActivityFamily> + AnyMode {
     fn update(self : Box<Self>) -> Box<dyn Activity>;
 }
 
@@ -110,5 +111,10 @@ fn main() {
     for _age in 18..100 {
         // Update the current Mode and/or transition to another Mode, when the current Mode requests it.
         Automaton::next(&mut person, |current_mode| current_mode.update());
+
+        // Fields that only exist on one Mode can be accessed by downcasting the current Mode to its concrete type.
+        if let Some(working) = Automaton::downcast_ref::<Working>(&person) {
+            println!("Hours worked today: {}", working.hours_worked);
+        }
     }
 }
\ No newline at end of file
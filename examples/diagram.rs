@@ -0,0 +1,109 @@
+// Copyright 2019 Andrew Thomas Christensen
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the
+// MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+use mode::{transitions, Automaton, Family, Introspect, Mode, NamedMode, StateGraph, TransitionTable};
+
+// This is the same busy beaver machine as in examples/turing.rs, except that the transitions are declared as a table
+// via the transitions! macro, which allows the state graph of the machine to be exported as a diagram.
+//
+
+const HEAD : u16 = 8;
+const MASK : u16 = 1 << HEAD;
+
+struct StateFamily;
+
+impl Family for StateFamily {
+    type Base = State;
+    type Mode = State;
+}
+
+// Allows the state graph of the Automaton to be exported as a diagram. (See main() below.)
+impl Introspect for StateFamily {
+    fn state_graph() -> StateGraph { StateGraph::from_table::<BusyBeaver>() }
+}
+
+#[derive(Copy, Clone, Debug)]
+enum PrintOp { Clear, Print }
+
+#[derive(Copy, Clone, Debug)]
+enum ShiftOp { Left,  Right }
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum State { A, B, C, D, E, H }
+
+impl Mode for State {
+    type Family = StateFamily;
+}
+
+// Names each State after the matching state in the BusyBeaver table, so that Introspect can find the current State.
+impl NamedMode for State {
+    fn mode_name(&self) -> &'static str { BusyBeaver::state_name(self) }
+}
+
+fn write(tape : &mut u16, print_op : PrintOp, shift_op : ShiftOp) {
+    print!("{:?}, {:?} ", print_op, shift_op);
+
+    match print_op {
+        PrintOp::Print => { *tape |=  1 << HEAD },
+        PrintOp::Clear => { *tape &= !(1 << HEAD) },
+    }
+
+    match shift_op {
+        ShiftOp::Left  => { *tape <<= 1 },
+        ShiftOp::Right => { *tape >>= 1 },
+    }
+}
+
+use PrintOp::*;
+use ShiftOp::*;
+
+// Each row reads "current state + bit under the head => next state / what to do to the tape". Since there are no rows
+// leading out of H, BusyBeaver::step() will return false in its "result" parameter once the machine has halted.
+transitions! {
+    struct BusyBeaver for State {
+        states { A, B, C, D, E, H }
+        fn step(bit : u16, tape : &mut u16);
+
+        A + 0 => H,
+        A + 1 => B / write(tape, Clear, Right),
+        B + 0 => C / write(tape, Clear, Right),
+        B + 1 => B / write(tape, Print, Right),
+        C + 0 => D / write(tape, Print,  Left),
+        C + 1 => C / write(tape, Print, Right),
+        D + 0 => E / write(tape, Clear,  Left),
+        D + 1 => D / write(tape, Print,  Left),
+        E + 0 => A / write(tape, Print, Right),
+        E + 1 => E / write(tape, Print,  Left),
+    }
+}
+
+fn main() {
+    let mut tape : u16 = 0b111 << HEAD;
+    let mut automaton = StateFamily::automaton_with_mode(State::A);
+
+    // Passing --dot, --mermaid, or --plantuml prints the state graph in that format instead of running the machine.
+    // Since the machine hasn't started yet, State::A will be highlighted as the current state.
+    let graph = Automaton::state_graph(&automaton);
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--dot" => { print!("{}", graph.to_dot()); return; },
+            "--mermaid" => { print!("{}", graph.to_mermaid()); return; },
+            "--plantuml" => { print!("{}", graph.to_plantuml()); return; },
+            _ => { },
+        }
+    }
+
+    while Automaton::next_with_result(&mut automaton, |current_state| {
+        let bit = (tape & MASK) >> HEAD;
+        print!("{:016b} {:?} + {}: ", tape, current_state, bit);
+
+        // The first tuple element will be interpreted as the next Mode to swap in. The second will become the return
+        // value of the Automaton::next_with_result() function.
+        let (next, stepped) = BusyBeaver::step(current_state, bit, &mut tape);
+        if stepped { println!("=> {:?}", next) } else { println!("Halted") }
+        (next, stepped)
+    }) { }
+}
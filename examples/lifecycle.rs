@@ -0,0 +1,134 @@
+// Copyright 2019 Andrew Thomas Christensen
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the
+// MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+// NOTE: This example is the same as the "Activity" example (examples/activity.rs), except that the code that should run
+// when entering or exiting each Mode has been moved out of update() and into the ModeLifecycle hooks.
+
+use mode::{Automaton, Family, Mode, ModeLifecycle};
+
+struct ActivityFamily;
+
+impl Family for ActivityFamily {
+    type Base = dyn Activity;
+    type Mode = Box<dyn Activity>;
+}
+
+// Making ModeLifecycle a supertrait of Activity allows the Automaton to call on_enter() and on_exit() on the current
+// Mode through a dyn Activity reference.
+//
+trait Activity : Mode<Family = ActivityFamily> + ModeLifecycle {
+    // Since Automaton::next_with_lifecycle() needs to call on_exit() on the current Mode after the next Mode has been
+    // created, update() borrows the current Mode instead of consuming it. Returning None means "don't transition".
+    //
+    fn update(&mut self) -> Option<Box<dyn Activity>>;
+}
+
+struct Working {
+    pub hours_worked : u32,
+}
+
+impl Mode for Working {
+    type Family = ActivityFamily;
+}
+
+impl ModeLifecycle for Working {
+    fn on_enter(&mut self) {
+        println!("Clocking in.");
+    }
+
+    fn on_exit(&mut self) {
+        println!("Time for {}!", if self.hours_worked == 4 { "lunch" } else { "dinner" });
+    }
+}
+
+impl Activity for Working {
+    fn update(&mut self) -> Option<Box<dyn Activity>> {
+        println!("Work, work, work...");
+        self.hours_worked += 1;
+
+        if self.hours_worked == 4 || self.hours_worked >= 8 {
+            Some(Box::new(Eating { hours_worked: self.hours_worked, calories_consumed: 0 }))
+        }
+        else { None }
+    }
+}
+
+struct Eating {
+    pub hours_worked : u32,
+    pub calories_consumed : u32,
+}
+
+impl Mode for Eating {
+    type Family = ActivityFamily;
+}
+
+impl ModeLifecycle for Eating {
+    fn on_exit(&mut self) {
+        println!("Ate {} calories.", self.calories_consumed);
+    }
+}
+
+impl Activity for Eating {
+    fn update(&mut self) -> Option<Box<dyn Activity>> {
+        println!("Yum!");
+        self.calories_consumed += 100;
+
+        if self.calories_consumed >= 500 {
+            if self.hours_worked >= 8 {
+                Some(Box::new(Sleeping { hours_rested: 0 }))
+            }
+            else {
+                Some(Box::new(Working { hours_worked: self.hours_worked }))
+            }
+        }
+        else { None }
+    }
+}
+
+struct Sleeping {
+    pub hours_rested : u32,
+}
+
+impl Mode for Sleeping {
+    type Family = ActivityFamily;
+}
+
+impl ModeLifecycle for Sleeping {
+    fn on_enter(&mut self) {
+        println!("Time for bed!");
+    }
+
+    fn on_exit(&mut self) {
+        println!("Time for breakfast!");
+    }
+}
+
+impl Activity for Sleeping {
+    fn update(&mut self) -> Option<Box<dyn Activity>> {
+        println!("ZzZzZzZz...");
+        self.hours_rested += 1;
+
+        if self.hours_rested >= 8 {
+            Some(Box::new(Eating { hours_worked: 0, calories_consumed: 0 }))
+        }
+        else { None }
+    }
+}
+
+fn main() {
+    let mut person = ActivityFamily::automaton_with_mode(Box::new(Working { hours_worked: 0 }));
+
+    // The initial Mode was never swapped in by a transition, so we need to enter it manually.
+    Automaton::enter(&mut person);
+
+    for _age in 18..100 {
+        // Update the current Mode and, if update() returns a new Mode, run the on_exit() and on_enter() hooks while
+        // swapping it in.
+        Automaton::next_with_lifecycle(&mut person, |current_mode| current_mode.update());
+    }
+
+    Automaton::exit(&mut person);
+}
@@ -29,6 +29,7 @@ impl Mode for State {
     type Family = StateFamily;
 }
 
+#[allow(clippy::assign_op_pattern)]
 fn step(state : State, tape : &mut u16) -> (State, bool) {
     use State::*;
     use PrintOp::*;
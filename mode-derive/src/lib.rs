@@ -0,0 +1,353 @@
+// Copyright 2019 Andrew Thomas Christensen
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the
+// MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+//! Derive macros for the [`mode`](https://docs.rs/mode) crate. These are re-exported from `mode` when its `derive`
+//! feature is enabled, so this crate should not need to be used directly.
+//! 
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{quote, quote_spanned};
+use syn::{
+    ext::IdentExt, parse_macro_input, parse_quote_spanned, spanned::Spanned, Attribute, Data, DeriveInput, Error,
+    Fields, Ident, Result, Type,
+};
+
+/// Implements `Mode` for a `struct` or `enum`, with the `Family` specified via a `#[mode(family = ...)]` attribute.
+/// 
+/// # Usage
+/// ```
+/// use mode::*;
+/// 
+/// #[derive(Family)]
+/// #[family(base = dyn Activity)]
+/// struct ActivityFamily;
+/// 
+/// trait Activity : Mode<Family = ActivityFamily> {
+///     fn update(self : Box<Self>) -> Box<dyn Activity>;
+/// }
+/// 
+/// #[derive(Mode)]
+/// #[mode(family = ActivityFamily)]
+/// struct Working { hours_worked : u32 }
+/// 
+/// impl Activity for Working {
+///     fn update(self : Box<Self>) -> Box<dyn Activity> { Box::new(Sleeping) }
+/// }
+/// 
+/// #[derive(Mode)]
+/// #[mode(family = ActivityFamily)]
+/// struct Sleeping;
+/// 
+/// impl Activity for Sleeping {
+///     fn update(self : Box<Self>) -> Box<dyn Activity> { self }
+/// }
+/// 
+/// let mut automaton = ActivityFamily::automaton_with_mode(Box::new(Working { hours_worked: 0 }));
+/// Automaton::next(&mut automaton, |current_mode| current_mode.update());
+/// ```
+/// 
+/// # Errors
+/// Leaving out the `#[mode(family = ...)]` attribute is a compile error, as is deriving `Mode` for a type that claims
+/// to be in a different `Family` than the one expected by its `Base` trait:
+/// ```compile_fail
+/// use mode::*;
+/// 
+/// #[derive(Family)]
+/// #[family(base = dyn Activity)]
+/// struct ActivityFamily;
+/// 
+/// #[derive(Family)]
+/// #[family(base = dyn Activity)]
+/// struct OtherFamily;
+/// 
+/// trait Activity : Mode<Family = ActivityFamily> { }
+/// 
+/// #[derive(Mode)]
+/// #[mode(family = OtherFamily)]
+/// struct Working;
+/// 
+/// impl Activity for Working { } // error: type mismatch resolving `<Working as Mode>::Family == ActivityFamily`
+/// ```
+/// 
+#[proc_macro_derive(Mode, attributes(mode))]
+pub fn derive_mode(input : TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_mode(&input).unwrap_or_else(Error::into_compile_error).into()
+}
+
+/// Implements `Family` for a type, with the `Base` and `Mode` types specified via a `#[family(...)]` attribute. This
+/// can be used in one of two ways, depending on how the states in the `Family` are represented.
+/// 
+/// # A `Family` where `Base` is a `dyn Trait`
+/// Deriving `Family` for a unit `struct` with a `#[family(base = ...)]` attribute implements `Family` for that
+/// `struct`, with the given `Base` type. If `base` is a `dyn Trait`, `Mode` defaults to `Box<dyn Trait>`. Otherwise,
+/// `Mode` defaults to the `Base` type itself. Either way, `Mode` can be overridden by adding, e.g.,
+/// `mode = Rc<dyn Trait>` to the attribute.
+/// 
+/// ```
+/// use mode::*;
+/// use std::rc::Rc;
+/// 
+/// #[derive(Family)]
+/// #[family(base = dyn Light)]
+/// struct LightFamily; // Mode = Box<dyn Light>
+/// 
+/// trait Light : Mode<Family = LightFamily> { }
+/// 
+/// #[derive(Family)]
+/// #[family(base = dyn Shared, mode = Rc<dyn Shared>)]
+/// struct SharedFamily;
+/// 
+/// trait Shared : Mode<Family = SharedFamily> { }
+/// ```
+/// 
+/// # A `Family` where `Base` is a concrete type
+/// Deriving `Family` for any other `struct` or `enum` with a `#[family(name = ...)]` attribute generates a new unit
+/// `struct` with the given name and the same visibility as the annotated type, and implements `Family` for it with the
+/// annotated type as both `Base` and `Mode`. Since the annotated type is the only `Mode` in the `Family`, `Mode` is
+/// implemented for it as well, so there is no need to `#[derive(Mode)]` separately.
+/// 
+/// ```
+/// use mode::*;
+/// 
+/// #[derive(Debug, Eq, Family, PartialEq)]
+/// #[family(name = ActivityFamily)]
+/// enum Activity {
+///     Working { hours_worked : u32 },
+///     Sleeping,
+/// }
+/// 
+/// let mut automaton = ActivityFamily::automaton_with_mode(Activity::Working { hours_worked: 0 });
+/// Automaton::next(&mut automaton, |_| Activity::Sleeping);
+/// assert_eq!(*automaton, Activity::Sleeping);
+/// ```
+/// 
+/// # Errors
+/// If the `Mode` type does not belong to the derived `Family`, the error will point at the `#[family(...)]` attribute:
+/// ```compile_fail
+/// use mode::*;
+/// 
+/// #[derive(Family)]
+/// #[family(base = dyn Activity)] // error: type mismatch resolving `<dyn Activity as Mode>::Family == ActivityFamily`
+/// struct ActivityFamily;
+/// 
+/// struct OtherFamily;
+/// impl Family for OtherFamily {
+///     type Base = dyn Activity;
+///     type Mode = Box<dyn Activity>;
+/// }
+/// 
+/// trait Activity : Mode<Family = OtherFamily> { }
+/// ```
+/// 
+#[proc_macro_derive(Family, attributes(family))]
+pub fn derive_family(input : TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_family(&input).unwrap_or_else(Error::into_compile_error).into()
+}
+
+/// Implements `NamedMode` for a `struct` or `enum`. For an `enum`, `mode_name()` returns the name of the current
+/// variant. For a `struct`, it returns the name of the `struct`.
+/// 
+/// # Usage
+/// ```
+/// use mode::*;
+/// 
+/// #[derive(Family, NamedMode)]
+/// #[family(name = ActivityFamily)]
+/// enum Activity {
+///     Working { hours_worked : u32 },
+///     Eating(u32),
+///     Sleeping,
+/// }
+/// 
+/// let mut automaton = ActivityFamily::automaton_with_mode(Activity::Working { hours_worked: 0 });
+/// assert_eq!(Automaton::current_mode_id(&automaton), ModeId::new::<Activity>("Working"));
+/// 
+/// Automaton::next(&mut automaton, |_| Activity::Eating(500));
+/// assert_eq!(automaton.mode_name(), "Eating");
+/// ```
+/// 
+#[proc_macro_derive(NamedMode)]
+pub fn derive_named_mode(input : TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand_named_mode(&input).unwrap_or_else(Error::into_compile_error).into()
+}
+
+fn expand_mode(input : &DeriveInput) -> Result<TokenStream2> {
+    let attr = find_attr(&input.attrs, "mode", "`#[derive(Mode)]` requires a `#[mode(family = ...)]` attribute")?;
+
+    let mut family : Option<Type> = None;
+    attr.parse_nested_meta(|meta| {
+        if meta.path.is_ident("family") {
+            set_once(&mut family, meta.value()?.parse()?, &meta.path)
+        }
+        else {
+            Err(meta.error("unknown `mode` attribute key, expected `family`"))
+        }
+    })?;
+
+    let family = family.ok_or_else(|| Error::new_spanned(attr, "expected `#[mode(family = ...)]`"))?;
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    // Spanning the associated type at the attribute makes errors about the Family type point at the user's code.
+    let family_type = quote_spanned! { family.span() => type Family = #family; };
+
+    Ok(quote! {
+        impl #impl_generics ::mode::Mode for #name #ty_generics #where_clause {
+            #family_type
+        }
+    })
+}
+
+fn expand_family(input : &DeriveInput) -> Result<TokenStream2> {
+    let attr = find_attr(&input.attrs, "family", "`#[derive(Family)]` requires a `#[family(base = ...)]` or \
+        `#[family(name = ...)]` attribute")?;
+
+    let mut name : Option<Ident> = None;
+    let mut base : Option<Type> = None;
+    let mut mode : Option<Type> = None;
+    attr.parse_nested_meta(|meta| {
+        if meta.path.is_ident("name") {
+            set_once(&mut name, meta.value()?.parse()?, &meta.path)
+        }
+        else if meta.path.is_ident("base") {
+            set_once(&mut base, meta.value()?.parse()?, &meta.path)
+        }
+        else if meta.path.is_ident("mode") {
+            set_once(&mut mode, meta.value()?.parse()?, &meta.path)
+        }
+        else {
+            Err(meta.error("unknown `family` attribute key, expected `name`, `base`, or `mode`"))
+        }
+    })?;
+
+    let is_unit_struct = match &input.data {
+        Data::Struct(data) => matches!(data.fields, Fields::Unit),
+        _ => false,
+    };
+
+    match (name, base) {
+        (Some(name), None) => {
+            if let Some(mode) = mode {
+                return Err(Error::new_spanned(mode, "`mode` cannot be combined with `name`, since the annotated type \
+                    is used as the `Mode`"));
+            }
+            expand_family_for_mode(input, &name)
+        },
+        (None, Some(base)) => {
+            if !is_unit_struct {
+                return Err(Error::new(input.ident.span(), "`#[family(base = ...)]` can only be used on a unit \
+                    `struct`; to generate a `Family` for this type, use `#[family(name = ...)]` instead"));
+            }
+            let mode = mode.unwrap_or_else(|| match &base {
+                Type::TraitObject(_) => parse_quote_spanned! { base.span() => ::mode::__private::Box<#base> },
+                _ => base.clone(),
+            });
+            expand_family_for_struct(input, &base, &mode)
+        },
+        (Some(name), Some(_)) => {
+            Err(Error::new_spanned(name, "`name` and `base` cannot be used together"))
+        },
+        (None, None) => {
+            Err(Error::new_spanned(attr, if is_unit_struct {
+                "expected `#[family(base = ...)]`"
+            }
+            else {
+                "expected `#[family(name = ...)]`"
+            }))
+        },
+    }
+}
+
+fn expand_family_for_struct(input : &DeriveInput, base : &Type, mode : &Type) -> Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let base_type = quote_spanned! { base.span() => type Base = #base; };
+    let mode_type = quote_spanned! { mode.span() => type Mode = #mode; };
+
+    Ok(quote! {
+        impl #impl_generics ::mode::Family for #name #ty_generics #where_clause {
+            #base_type
+            #mode_type
+        }
+    })
+}
+
+fn expand_family_for_mode(input : &DeriveInput, family : &Ident) -> Result<TokenStream2> {
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(&input.generics, "`#[family(name = ...)]` cannot be used on a generic type"));
+    }
+
+    let vis = &input.vis;
+    let name = &input.ident;
+    let doc = format!("The `Family` of [`{}`], generated by `#[derive(Family)]`.", name);
+
+    Ok(quote! {
+        #[doc = #doc]
+        #vis struct #family;
+
+        impl ::mode::Family for #family {
+            type Base = #name;
+            type Mode = #name;
+        }
+
+        impl ::mode::Mode for #name {
+            type Family = #family;
+        }
+    })
+}
+
+fn expand_named_mode(input : &DeriveInput) -> Result<TokenStream2> {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let body = match &input.data {
+        Data::Enum(data) if data.variants.is_empty() => quote! { match *self { } },
+        Data::Enum(data) => {
+            let arms = data.variants.iter().map(|variant| {
+                let ident = &variant.ident;
+                let variant_name = ident.unraw().to_string();
+                quote! { Self::#ident { .. } => #variant_name, }
+            });
+            quote! { match self { #(#arms)* } }
+        },
+        Data::Struct(_) => {
+            let struct_name = name.unraw().to_string();
+            quote! { #struct_name }
+        },
+        Data::Union(_) => return Err(Error::new(name.span(), "`#[derive(NamedMode)]` cannot be used on a `union`")),
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::mode::NamedMode for #name #ty_generics #where_clause {
+            fn mode_name(&self) -> &'static str {
+                #body
+            }
+        }
+    })
+}
+
+fn find_attr<'a>(attrs : &'a [Attribute], name : &str, missing : &str) -> Result<&'a Attribute> {
+    let mut found = attrs.iter().filter(|attr| attr.path().is_ident(name));
+    let attr = found.next().ok_or_else(|| Error::new(Span::call_site(), missing))?;
+    if let Some(duplicate) = found.next() {
+        return Err(Error::new_spanned(duplicate, format!("duplicate `#[{}(...)]` attribute", name)));
+    }
+    Ok(attr)
+}
+
+fn set_once<T>(slot : &mut Option<T>, value : T, path : &syn::Path) -> Result<()> {
+    if slot.is_some() {
+        return Err(Error::new_spanned(path, "duplicate key"));
+    }
+    *slot = Some(value);
+    Ok(())
+}
@@ -4,13 +4,16 @@
 // MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option. This file may not be copied,
 // modified, or distributed except according to those terms.
 
-use crate::Family;
-use std::{
+use crate::{Family, PoisonError};
+#[cfg(feature = "tracing")]
+use crate::trace::Tracer;
+use core::{
     convert::{AsRef, AsMut},
     borrow::{Borrow, BorrowMut},
     fmt,
+    future::Future,
 };
-use std::ops::{Deref, DerefMut};
+use core::ops::{Deref, DerefMut};
 
 /// Represents a state machine over a set of `Mode`s within the same `Family`.
 /// 
@@ -23,6 +26,7 @@ use std::ops::{Deref, DerefMut};
 /// 
 /// # Usage
 /// ```
+/// # #[cfg(feature = "alloc")] {
 /// use mode::*;
 /// #
 /// # struct SomeFamily;
@@ -64,6 +68,7 @@ use std::ops::{Deref, DerefMut};
 /// // next() can be used to transition the Automaton to a different Mode, or, as in this case, to allow the current
 /// // Mode to transition itself when ready.
 /// Automaton::next(&mut automaton, |current_mode| current_mode.some_transition_fn());
+/// # }
 /// ```
 /// 
 /// # The `F` parameter
@@ -122,6 +127,7 @@ use std::ops::{Deref, DerefMut};
 /// (See example below.)
 /// 
 /// ```
+/// # #[cfg(feature = "alloc")] {
 /// use mode::*;
 /// 
 /// struct ReallyBigFamily;
@@ -150,14 +156,29 @@ use std::ops::{Deref, DerefMut};
 ///     // This moves the Box back out of the function, not the ReallyBigMode object itself, which is *much* cheaper!
 ///     Automaton::next(&mut automaton, |current_mode| current_mode);
 /// }
+/// # }
 /// ```
 /// 
 /// For more on the `Base` and `Mode` parameters, see [`Family`](trait.Family.html).
 /// 
+/// # Poisoning
+/// 
+/// Since the current `Mode` is moved into the transition function passed to `Automaton::next()`, a transition function
+/// that panics will leave the `Automaton` without a current `Mode`. If the panic is caught, e.g. via
+/// `std::panic::catch_unwind()`, the `Automaton` is considered **poisoned**, much like a `std::sync::Mutex` whose guard
+/// was dropped during a panic. A poisoned `Automaton` will panic if `borrow_mode()`, `borrow_mode_mut()`, or `next()`
+/// is called on it. Use [`is_poisoned()`](#method.is_poisoned) or [`try_borrow_mode()`](#method.try_borrow_mode) to
+/// detect this condition, and [`recover_with()`](#method.recover_with) to repair the `Automaton` with a new `Mode`.
+/// 
+/// An `Automaton` is also poisoned if the `Future` returned from [`next_async()`](#method.next_async) is dropped while
+/// the transition is still pending.
+/// 
 pub struct Automaton<F>
     where F : Family + ?Sized
 {
-    mode : Option<F::Mode>,
+    pub(crate) mode : Option<F::Mode>,
+    #[cfg(feature = "tracing")]
+    pub(crate) tracer : Option<Tracer<F>>,
 }
 
 impl<F> Automaton<F>
@@ -197,6 +218,8 @@ impl<F> Automaton<F>
     pub fn with_mode(mode : F::Mode) -> Self {
         Self {
             mode : Some(mode),
+            #[cfg(feature = "tracing")]
+            tracer : None,
         }
     }
 
@@ -287,11 +310,307 @@ impl<F> Automaton<F>
     pub fn next_with_result<T, R>(automaton : &mut Self, transition_fn : T) -> R
         where T : FnOnce(F::Mode) -> (F::Mode, R)
     {
-        let (next_mode, result) = transition_fn(
-            automaton.mode.take().expect("Cannot swap out current Mode while another swap is taking place!"));
-        automaton.mode = Some(next_mode);
+        let current_mode = automaton.begin_transition()
+            .expect("Cannot swap out current Mode because the Automaton is poisoned!");
+        let (next_mode, result) = transition_fn(current_mode);
+        automaton.end_transition(next_mode);
+        result
+    }
+
+    /// Calls `transition_fn` on the current `Mode` to attempt a transition that may fail. If `transition_fn` returns
+    /// `Ok` with a new `Mode`, that `Mode` is swapped in as current and `Ok(())` is returned. If it returns `Err` with
+    /// a tuple of a `Mode` and an error, that `Mode` is swapped back in as current and the error is returned.
+    /// 
+    /// By convention, `transition_fn` should hand the **original** `Mode` back in the `Err` case, so that a failed
+    /// transition leaves the `Automaton` exactly as it was before `try_next()` was called. Since the `Mode` is moved
+    /// into `transition_fn` by value, any state that the transition moved out of the `Mode` before failing should be
+    /// moved back in before returning the error.
+    /// 
+    /// # Usage
+    /// ```
+    /// use mode::*;
+    /// 
+    /// struct ConnectionFamily;
+    /// impl Family for ConnectionFamily {
+    ///     type Base = Connection;
+    ///     type Mode = Connection;
+    /// }
+    /// 
+    /// #[derive(Debug, Eq, PartialEq)]
+    /// enum Connection {
+    ///     Closed,
+    ///     Open { address : String },
+    /// }
+    /// 
+    /// impl Mode for Connection { type Family = ConnectionFamily; }
+    /// 
+    /// #[derive(Debug, Eq, PartialEq)]
+    /// enum ConnectError { AlreadyOpen, BadAddress }
+    /// 
+    /// impl Connection {
+    ///     fn connect(self, address : &str) -> Result<Self, (Self, ConnectError)> {
+    ///         match self {
+    ///             Connection::Closed if address.is_empty() => Err((self, ConnectError::BadAddress)),
+    ///             Connection::Closed => Ok(Connection::Open { address: address.to_owned() }),
+    ///             Connection::Open { .. } => Err((self, ConnectError::AlreadyOpen)),
+    ///         }
+    ///     }
+    /// }
+    /// 
+    /// let mut automaton = ConnectionFamily::automaton_with_mode(Connection::Closed);
+    /// 
+    /// // A failed transition surfaces the error and leaves the original Mode in place.
+    /// assert_eq!(Automaton::try_next(&mut automaton, |mode| mode.connect("")), Err(ConnectError::BadAddress));
+    /// assert_eq!(*automaton, Connection::Closed);
+    /// 
+    /// // A successful transition swaps in the new Mode.
+    /// assert_eq!(Automaton::try_next(&mut automaton, |mode| mode.connect("localhost")), Ok(()));
+    /// assert_eq!(*automaton, Connection::Open { address: "localhost".to_owned() });
+    /// ```
+    /// 
+    pub fn try_next<T, E>(automaton : &mut Self, transition_fn : T) -> Result<(), E>
+        where T : FnOnce(F::Mode) -> Result<F::Mode, (F::Mode, E)>
+    {
+        Self::try_next_with_result(automaton, |mode| transition_fn(mode).map(|mode| (mode, ())))
+    }
+
+    /// Calls `transition_fn` on the current `Mode` to attempt a transition that may fail, returning either a result
+    /// value or an error. This is the same as [`try_next()`](struct.Automaton.html#method.try_next), except that the
+    /// `Ok` case of `transition_fn` returns a tuple containing the new `Mode` to swap in as well as a return value,
+    /// which will be returned from this function in an `Ok` after the new `Mode` is swapped in.
+    /// 
+    /// As with `try_next()`, `transition_fn` should hand the **original** `Mode` back in the `Err` case, so that a
+    /// failed transition leaves the `Automaton` as it was before this function was called.
+    /// 
+    /// # Usage
+    /// ```
+    /// use mode::*;
+    /// 
+    /// struct CounterFamily;
+    /// impl Family for CounterFamily {
+    ///     type Base = Counter;
+    ///     type Mode = Counter;
+    /// }
+    /// 
+    /// #[derive(Debug, Eq, PartialEq)]
+    /// struct Counter { count : u8 }
+    /// impl Mode for Counter { type Family = CounterFamily; }
+    /// 
+    /// let mut automaton = CounterFamily::automaton_with_mode(Counter { count: 254 });
+    /// 
+    /// let increment = |counter : Counter| {
+    ///     match counter.count.checked_add(1) {
+    ///         Some(count) => Ok((Counter { count }, count)),
+    ///         None => Err((counter, "Counter overflowed!")),
+    ///     }
+    /// };
+    /// 
+    /// assert_eq!(Automaton::try_next_with_result(&mut automaton, increment), Ok(255));
+    /// assert_eq!(Automaton::try_next_with_result(&mut automaton, increment), Err("Counter overflowed!"));
+    /// assert_eq!(automaton.count, 255);
+    /// ```
+    /// 
+    pub fn try_next_with_result<T, R, E>(automaton : &mut Self, transition_fn : T) -> Result<R, E>
+        where T : FnOnce(F::Mode) -> Result<(F::Mode, R), (F::Mode, E)>
+    {
+        Self::next_with_result(automaton, |mode| {
+            match transition_fn(mode) {
+                Ok((next_mode, result)) => (next_mode, Ok(result)),
+                Err((previous_mode, error)) => (previous_mode, Err(error)),
+            }
+        })
+    }
+
+    /// Asynchronous version of [`next()`](struct.Automaton.html#method.next). Calls `transition_fn` on the current
+    /// `Mode` and awaits the `Future` it returns, swapping in whatever `Mode` the `Future` resolves to. This allows
+    /// transitions that need to wait on I/O, e.g. loading assets or receiving a message from a channel.
+    /// 
+    /// This function does not depend on any particular executor, so the returned `Future` can be driven by any async
+    /// runtime.
+    /// 
+    /// # While the `Future` is pending
+    /// The returned `Future` holds a mutable borrow of the `Automaton` until it completes, so the `Automaton` cannot be
+    /// accessed in any way, e.g. via `borrow_mode()`, while the transition is pending. This is enforced at compile
+    /// time.
+    /// 
+    /// # Cancellation
+    /// Since the current `Mode` is moved into `transition_fn` by value, dropping the returned `Future` before it
+    /// completes also drops the current `Mode`, along with anything else the pending transition owned. In that case,
+    /// the `Automaton` is left **poisoned**, exactly as if the transition function had panicked. Use
+    /// [`is_poisoned()`](struct.Automaton.html#method.is_poisoned) to detect this condition, and
+    /// [`recover_with()`](struct.Automaton.html#method.recover_with) to repair the `Automaton`. Dropping the `Future`
+    /// before it is polled for the first time is safe, and leaves the `Automaton` untouched.
+    /// 
+    /// # Usage
+    /// ```
+    /// use mode::*;
+    /// use std::{future::Future, pin::Pin, task::{Context, Poll, RawWaker, RawWakerVTable, Waker}};
+    /// 
+    /// # fn noop_waker() -> Waker {
+    /// #     fn clone(_ : *const ()) -> RawWaker { RawWaker::new(std::ptr::null(), &VTABLE) }
+    /// #     fn noop(_ : *const ()) { }
+    /// #     static VTABLE : RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+    /// #     unsafe { Waker::from_raw(RawWaker::new(std::ptr::null(), &VTABLE)) }
+    /// # }
+    /// #
+    /// // A Future that is pending the first time it is polled, and ready the second time.
+    /// struct YieldOnce(bool);
+    /// impl Future for YieldOnce {
+    ///     type Output = ();
+    ///     fn poll(mut self : Pin<&mut Self>, context : &mut Context<'_>) -> Poll<()> {
+    ///         if self.0 { return Poll::Ready(()); }
+    ///         self.0 = true;
+    ///         context.waker().wake_by_ref();
+    ///         Poll::Pending
+    ///     }
+    /// }
+    /// 
+    /// struct LoaderFamily;
+    /// impl Family for LoaderFamily {
+    ///     type Base = Loader;
+    ///     type Mode = Loader;
+    /// }
+    /// 
+    /// #[derive(Debug, Eq, PartialEq)]
+    /// enum Loader { Loading, Loaded(Vec<u8>) }
+    /// impl Mode for Loader { type Family = LoaderFamily; }
+    /// 
+    /// async fn load(_mode : Loader) -> Loader {
+    ///     YieldOnce(false).await; // Pretend to wait on some I/O.
+    ///     Loader::Loaded(vec![1, 2, 3])
+    /// }
+    /// 
+    /// let waker = noop_waker();
+    /// let mut context = Context::from_waker(&waker);
+    /// 
+    /// // Drive a transition to completion with a simple, local executor.
+    /// let mut automaton = LoaderFamily::automaton_with_mode(Loader::Loading);
+    /// {
+    ///     let mut future = Box::pin(Automaton::next_async(&mut automaton, load));
+    ///     while future.as_mut().poll(&mut context).is_pending() { }
+    /// }
+    /// assert_eq!(*automaton, Loader::Loaded(vec![1, 2, 3]));
+    /// 
+    /// // Cancel a transition while it is pending. This poisons the Automaton.
+    /// let mut automaton = LoaderFamily::automaton_with_mode(Loader::Loading);
+    /// {
+    ///     let mut future = Box::pin(Automaton::next_async(&mut automaton, load));
+    ///     assert!(future.as_mut().poll(&mut context).is_pending());
+    /// }
+    /// assert!(Automaton::is_poisoned(&automaton));
+    /// ```
+    /// 
+    pub async fn next_async<T, U>(automaton : &mut Self, transition_fn : T)
+        where
+            T : FnOnce(F::Mode) -> U,
+            U : Future<Output = F::Mode>,
+    {
+        Self::next_with_result_async(automaton, |mode| {
+            let future = transition_fn(mode);
+            async { (future.await, ()) }
+        }).await
+    }
+
+    /// Asynchronous version of [`next_with_result()`](struct.Automaton.html#method.next_with_result). Calls
+    /// `transition_fn` on the current `Mode` and awaits the `Future` it returns, which should resolve to a tuple
+    /// containing the new `Mode` to swap in as well as a return value, which will be returned from this function after
+    /// the new `Mode` is swapped in.
+    /// 
+    /// See [`next_async()`](struct.Automaton.html#method.next_async) for details on what happens while the `Future`
+    /// is pending and if it is dropped before completing.
+    /// 
+    pub async fn next_with_result_async<T, U, R>(automaton : &mut Self, transition_fn : T) -> R
+        where
+            T : FnOnce(F::Mode) -> U,
+            U : Future<Output = (F::Mode, R)>,
+    {
+        let current_mode = automaton.begin_transition()
+            .expect("Cannot swap out current Mode because the Automaton is poisoned!");
+        let (next_mode, result) = transition_fn(current_mode).await;
+        automaton.end_transition(next_mode);
         result
     }
+
+    /// Swaps the current `Mode` out of the `Automaton` so that it can be moved into a transition function, notifying
+    /// the tracer, if any. Returns `None` if the `Automaton` is poisoned. Every successful call to this function should
+    /// be followed by a call to `end_transition()`, unless the transition function panics.
+    /// 
+    pub(crate) fn begin_transition(&mut self) -> Option<F::Mode> {
+        let current_mode = self.mode.take()?;
+        #[cfg(feature = "tracing")]
+        if let Some(tracer) = self.tracer.as_mut() {
+            tracer.before_transition(&current_mode);
+        }
+        Some(current_mode)
+    }
+
+    /// Swaps `next_mode` into the `Automaton` as current after a transition function returns, notifying the tracer, if
+    /// any.
+    /// 
+    pub(crate) fn end_transition(&mut self, next_mode : F::Mode) {
+        #[cfg(feature = "tracing")]
+        if let Some(tracer) = self.tracer.as_mut() {
+            tracer.after_transition(&next_mode);
+        }
+        self.mode = Some(next_mode);
+    }
+
+    /// Returns `true` if a transition function panicked while the current `Mode` was swapped out of the `Automaton`,
+    /// leaving it without a current `Mode`. A poisoned `Automaton` will panic if any function that needs the current
+    /// `Mode` is called on it. See [`recover_with()`](struct.Automaton.html#method.recover_with) for how to repair it.
+    /// 
+    /// **NOTE:** Since `Automaton` implements `Deref`, this is an associated function rather than a method, so that it
+    /// doesn't shadow any function with the same name on `F::Base`. Call it as `Automaton::is_poisoned(&automaton)`.
+    /// 
+    /// # Usage
+    /// ```
+    /// use mode::*;
+    /// use std::panic::{self, AssertUnwindSafe};
+    /// 
+    /// struct SomeFamily;
+    /// impl Family for SomeFamily {
+    ///     type Base = SomeMode;
+    ///     type Mode = SomeMode;
+    /// }
+    /// 
+    /// #[derive(Debug, Eq, PartialEq)]
+    /// enum SomeMode { A, B }
+    /// impl Mode for SomeMode { type Family = SomeFamily; }
+    /// 
+    /// let mut automaton = SomeFamily::automaton_with_mode(SomeMode::A);
+    /// assert!(!Automaton::is_poisoned(&automaton));
+    /// 
+    /// // Panic in the middle of a transition, and catch the panic.
+    /// let result = panic::catch_unwind(AssertUnwindSafe(|| {
+    ///     Automaton::next(&mut automaton, |_| panic!("Something went wrong!"));
+    /// }));
+    /// assert!(result.is_err());
+    /// assert!(Automaton::is_poisoned(&automaton));
+    /// assert!(automaton.try_borrow_mode().is_err());
+    /// 
+    /// // Repair the Automaton by swapping in a new Mode.
+    /// assert!(Automaton::recover_with(&mut automaton, SomeMode::B).is_ok());
+    /// assert!(!Automaton::is_poisoned(&automaton));
+    /// assert_eq!(*automaton, SomeMode::B);
+    /// ```
+    /// 
+    pub fn is_poisoned(automaton : &Self) -> bool {
+        automaton.mode.is_none()
+    }
+
+    /// Repairs a poisoned `Automaton` by making `mode` the current `Mode`, returning `Ok(())`. If the `Automaton` is
+    /// **not** poisoned, the current `Mode` is left untouched and `mode` is handed back to the caller as an `Err`.
+    /// 
+    /// See [`is_poisoned()`](struct.Automaton.html#method.is_poisoned) for more details.
+    /// 
+    pub fn recover_with(automaton : &mut Self, mode : F::Mode) -> Result<(), F::Mode> {
+        if automaton.mode.is_some() {
+            return Err(mode);
+        }
+
+        automaton.mode = Some(mode);
+        Ok(())
+    }
 }
 
 impl<F> Automaton<F>
@@ -307,9 +626,19 @@ impl<F> Automaton<F>
     /// `Automaton` as if it were an object of type `Base`.
     /// 
     pub fn borrow_mode(&self) -> &F::Base {
+        self.try_borrow_mode()
+            .expect("Cannot borrow current Mode because the Automaton is poisoned!")
+    }
+
+    /// Returns an immutable reference to the current `Mode` as an `&F::Base`, or a `PoisonError` if a previous
+    /// transition function panicked and left the `Automaton` without a current `Mode`.
+    /// 
+    /// See [`is_poisoned()`](struct.Automaton.html#method.is_poisoned) for more details.
+    /// 
+    pub fn try_borrow_mode(&self) -> Result<&F::Base, PoisonError> {
         self.mode.as_ref()
-            .expect("Cannot borrow current Mode because another swap is already taking place!")
-            .borrow()
+            .map(Borrow::borrow)
+            .ok_or_else(PoisonError::new)
     }
 }
 
@@ -326,9 +655,19 @@ impl<F> Automaton<F>
     /// `Automaton` as if it were an object of type `Base`.
     /// 
     pub fn borrow_mode_mut(&mut self) -> &mut F::Base {
+        self.try_borrow_mode_mut()
+            .expect("Cannot borrow current Mode because the Automaton is poisoned!")
+    }
+
+    /// Returns a mutable reference to the current `Mode` as a `&mut F::Base`, or a `PoisonError` if a previous
+    /// transition function panicked and left the `Automaton` without a current `Mode`.
+    /// 
+    /// See [`is_poisoned()`](struct.Automaton.html#method.is_poisoned) for more details.
+    /// 
+    pub fn try_borrow_mode_mut(&mut self) -> Result<&mut F::Base, PoisonError> {
         self.mode.as_mut()
-            .expect("Cannot borrow current Mode because another swap is already taking place!")
-            .borrow_mut()
+            .map(BorrowMut::borrow_mut)
+            .ok_or_else(PoisonError::new)
     }
 }
 
@@ -449,6 +788,8 @@ impl<F> Automaton<F>
     pub fn new() -> Self {
         Self {
             mode : Some(Default::default()),
+            #[cfg(feature = "tracing")]
+            tracer : None,
         }
     }
 }
@@ -467,10 +808,12 @@ impl<F> Default for Automaton<F>
     }
 }
 
-/// If `Base` implements `std::fmt::Debug`, `Automaton` also implements `Debug`, and will print its current `mode`.
+/// If `Base` implements `std::fmt::Debug`, `Automaton` also implements `Debug`, and will print its current `mode`. A
+/// poisoned `Automaton` will print its `mode` as `<poisoned>`.
 /// 
 /// # Usage
 /// ```
+/// # #[cfg(feature = "alloc")] {
 /// use mode::*;
 /// use std::fmt::Debug;
 /// 
@@ -496,6 +839,7 @@ impl<F> Default for Automaton<F>
 /// 
 /// let automaton = MyFamily::automaton_with_mode(Box::new(MyMode { foo: 3, bar: "Hello, World!" }));
 /// dbg!(automaton);
+/// # }
 /// ```
 /// 
 impl<F> fmt::Debug for Automaton<F>
@@ -505,9 +849,12 @@ impl<F> fmt::Debug for Automaton<F>
         F::Base : fmt::Debug,
 {
     fn fmt(&self, formatter : &mut fmt::Formatter) -> fmt::Result {
-        formatter.debug_struct("Automaton")
-            .field("mode", &self.borrow_mode())
-            .finish()
+        let mut debug = formatter.debug_struct("Automaton");
+        match self.try_borrow_mode() {
+            Ok(mode) => debug.field("mode", &mode),
+            Err(_) => debug.field("mode", &format_args!("<poisoned>")),
+        };
+        debug.finish()
     }
 }
 
@@ -515,6 +862,7 @@ impl<F> fmt::Debug for Automaton<F>
 /// 
 /// # Usage
 /// ```
+/// # #[cfg(feature = "alloc")] {
 /// use mode::*;
 /// use std::fmt::{Display, Formatter, Result};
 /// 
@@ -545,6 +893,7 @@ impl<F> fmt::Debug for Automaton<F>
 /// 
 /// let automaton = MyFamily::automaton_with_mode(Box::new(MyMode { foo: 3, bar: "Hello, World!" }));
 /// println!("{}", automaton);
+/// # }
 /// ```
 /// 
 impl<F> fmt::Display for Automaton<F>
@@ -0,0 +1,124 @@
+// Copyright 2019 Andrew Thomas Christensen
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the
+// MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+use core::{cell::Cell, time::Duration};
+#[cfg(feature = "alloc")]
+use alloc::rc::Rc;
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+use alloc::sync::Arc;
+#[cfg(feature = "std")]
+use std::time::Instant;
+
+/// Trait for a source of time, used by features that need to measure how long an `Automaton` has been in a `Mode`,
+/// e.g. [`TimedAutomaton`](struct.TimedAutomaton.html).
+/// 
+/// Time is represented as a `Duration` elapsed since some fixed, arbitrary epoch chosen by the `Clock`, which allows
+/// both real clocks and virtual clocks to be used interchangeably. Two time values are only comparable if they came
+/// from the same `Clock`.
+/// 
+/// Use [`InstantClock`](struct.InstantClock.html) to measure real time, or [`ManualClock`](struct.ManualClock.html) to
+/// control the passage of time explicitly, e.g. for deterministic tests. Since a `Clock` only needs a shared reference
+/// to report the current time, it is also implemented for `&C`, `Rc<C>`, and `Arc<C>`, so that the same `Clock` can be
+/// shared between an `Automaton` wrapper and the code that drives it.
+/// 
+pub trait Clock {
+    /// Returns the time elapsed since the epoch of this `Clock`. This should never go backwards.
+    /// 
+    fn now(&self) -> Duration;
+}
+
+impl<C> Clock for &C
+    where C : Clock + ?Sized
+{
+    fn now(&self) -> Duration { (**self).now() }
+}
+
+#[cfg(feature = "alloc")]
+impl<C> Clock for Rc<C>
+    where C : Clock + ?Sized
+{
+    fn now(&self) -> Duration { (**self).now() }
+}
+
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+impl<C> Clock for Arc<C>
+    where C : Clock + ?Sized
+{
+    fn now(&self) -> Duration { (**self).now() }
+}
+
+/// A `Clock` that measures real time via `std::time::Instant`. The epoch of the `Clock` is the moment it was created.
+/// 
+/// This requires the `std` feature.
+/// 
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, Debug)]
+pub struct InstantClock {
+    epoch : Instant,
+}
+
+#[cfg(feature = "std")]
+impl InstantClock {
+    /// Creates a new `InstantClock` whose epoch is the current `Instant`.
+    /// 
+    pub fn new() -> Self {
+        Self { epoch: Instant::now() }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Default for InstantClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Clock for InstantClock {
+    fn now(&self) -> Duration {
+        self.epoch.elapsed()
+    }
+}
+
+/// A virtual `Clock` that only moves forward when told to, via [`advance()`](#method.advance). This is useful for
+/// testing time-dependent behavior deterministically, without having to actually wait.
+/// 
+/// # Usage
+/// ```
+/// use mode::*;
+/// use std::time::Duration;
+/// 
+/// let clock = ManualClock::new();
+/// assert_eq!(clock.now(), Duration::from_secs(0));
+/// 
+/// clock.advance(Duration::from_millis(1500));
+/// assert_eq!(clock.now(), Duration::from_millis(1500));
+/// ```
+/// 
+#[derive(Clone, Debug, Default)]
+pub struct ManualClock {
+    now : Cell<Duration>,
+}
+
+impl ManualClock {
+    /// Creates a new `ManualClock`, starting at the epoch.
+    /// 
+    pub fn new() -> Self {
+        Self { now: Cell::new(Duration::from_secs(0)) }
+    }
+
+    /// Moves the `ManualClock` forward by `duration`.
+    /// 
+    pub fn advance(&self, duration : Duration) {
+        self.now.set(self.now.get() + duration);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Duration {
+        self.now.get()
+    }
+}
@@ -0,0 +1,815 @@
+// Copyright 2019 Andrew Thomas Christensen
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the
+// MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+use crate::{Automaton, Family, NamedMode, TransitionTable};
+use alloc::{borrow::ToOwned, format, string::{String, ToString}, vec::Vec};
+use core::{borrow::Borrow, fmt};
+
+/// A description of the states in a state machine and the edges between them, which can be exported as a diagram via
+/// [`to_dot()`](#method.to_dot), [`to_mermaid()`](#method.to_mermaid), or [`to_plantuml()`](#method.to_plantuml).
+/// 
+/// A `StateGraph` can be built up by hand, by calling [`add_state()`](#method.add_state) and
+/// [`add_edge()`](#method.add_edge), or generated from a [`TransitionTable`](trait.TransitionTable.html) via
+/// [`from_table()`](#method.from_table). To generate a `StateGraph` from a live `Automaton`, with its current `Mode`
+/// highlighted, implement [`Introspect`](trait.Introspect.html) for its `Family`.
+/// 
+/// A state can also be given a nested `StateGraph` of its own via
+/// [`add_composite_state()`](#method.add_composite_state), e.g. to describe the child `Automaton` of a
+/// [`HierarchicalMode`](trait.HierarchicalMode.html). State names and edge labels can contain any characters. Each
+/// output format quotes or escapes them as needed, e.g. a state named `Turn-Left` is declared with a quoted label under
+/// an escaped ID in Mermaid and PlantUML, since those formats only accept identifiers as state IDs.
+/// 
+/// # Usage
+/// ```
+/// use mode::StateGraph;
+/// 
+/// let mut graph = StateGraph::with_name("Door");
+/// graph.add_state("Open");
+/// graph.add_state("Closed");
+/// graph.add_edge("Open", "Closed", Some("push"));
+/// graph.add_edge("Closed", "Open", Some("pull"));
+/// graph.set_current("Closed");
+/// 
+/// assert_eq!(graph.to_dot(), "\
+/// digraph \"Door\" {
+///     \"Open\";
+///     \"Closed\" [style=filled, fillcolor=lightgrey];
+///     \"Open\" -> \"Closed\" [label=\"push\"];
+///     \"Closed\" -> \"Open\" [label=\"pull\"];
+/// }
+/// ");
+/// ```
+/// 
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct StateGraph {
+    name : Option<String>,
+    states : Vec<String>,
+    edges : Vec<GraphEdge>,
+    substates : Vec<(String, StateGraph)>,
+    initial : Option<String>,
+    current : Option<String>,
+}
+
+/// An edge between two states in a [`StateGraph`](struct.StateGraph.html).
+/// 
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GraphEdge {
+    /// The name of the state this edge leads out of.
+    /// 
+    pub from : String,
+
+    /// The name of the state this edge leads into.
+    /// 
+    pub to : String,
+
+    /// A description of what triggers this edge, if any.
+    /// 
+    pub label : Option<String>,
+}
+
+impl StateGraph {
+    /// Creates a new, empty `StateGraph`.
+    /// 
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new, empty `StateGraph` with the specified `name`, which will be used as the title of the diagram.
+    /// 
+    pub fn with_name<N : Into<String>>(name : N) -> Self {
+        Self { name: Some(name.into()), ..Self::default() }
+    }
+
+    /// Creates a new `StateGraph` containing all states and edges in the `TransitionTable` `T`. Each edge is labeled
+    /// with the input pattern of the corresponding row, followed by its guard in square brackets and its action after a
+    /// slash, if present, e.g. `Input::Coin [coins > 0] / count(coins)`.
+    /// 
+    pub fn from_table<T>() -> Self
+        where T : TransitionTable + ?Sized
+    {
+        let mut graph = Self::new();
+
+        for state in T::STATES {
+            graph.add_state(T::state_name(state));
+        }
+
+        for edge in T::EDGES {
+            let mut label = edge.input.to_owned();
+            if let Some(guard) = edge.guard {
+                label.push_str(&format!(" [{}]", guard));
+            }
+            if let Some(action) = edge.action {
+                label.push_str(&format!(" / {}", action));
+            }
+            graph.add_edge(T::state_name(&edge.from), T::state_name(&edge.to), Some(label));
+        }
+
+        graph
+    }
+
+    /// Returns the name of the `StateGraph`, if any.
+    /// 
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Adds a state with the specified `name` to the `StateGraph`, if a state with that name doesn't already exist.
+    /// 
+    pub fn add_state<N : Into<String>>(&mut self, name : N) {
+        let name = name.into();
+        if !self.states.contains(&name) {
+            self.states.push(name);
+        }
+    }
+
+    /// Adds an edge between the states named `from` and `to`, with an optional `label` describing what triggers it.
+    /// 
+    pub fn add_edge<A, B, L>(&mut self, from : A, to : B, label : Option<L>)
+        where
+            A : Into<String>,
+            B : Into<String>,
+            L : Into<String>,
+    {
+        self.edges.push(GraphEdge { from: from.into(), to: to.into(), label: label.map(Into::into) });
+    }
+
+    /// Adds a composite state with the specified `name` to the `StateGraph`, containing all states and edges in
+    /// `substates`. If a state with that name already exists, its nested `StateGraph` will be replaced.
+    /// 
+    pub fn add_composite_state<N : Into<String>>(&mut self, name : N, substates : StateGraph) {
+        let name = name.into();
+        self.add_state(name.clone());
+        self.substates.retain(|(existing, _)| *existing != name);
+        self.substates.push((name, substates));
+    }
+
+    /// Marks the state with the specified `name` as the initial state, which will be pointed to by a start marker in
+    /// the diagram.
+    /// 
+    /// See [`Automaton::initial_state_graph()`](struct.Automaton.html#method.initial_state_graph) for a way to do this
+    /// automatically, based on the `Default` `Mode` of a `Family`.
+    /// 
+    pub fn set_initial<N : Into<String>>(&mut self, name : N) {
+        self.initial = Some(name.into());
+    }
+
+    /// Marks the state with the specified `name` as the current state, which will be highlighted in the diagram.
+    /// 
+    pub fn set_current<N : Into<String>>(&mut self, name : N) {
+        self.current = Some(name.into());
+    }
+
+    /// Returns the names of all states in the `StateGraph`, in the order they were added.
+    /// 
+    pub fn states(&self) -> &[String] {
+        &self.states
+    }
+
+    /// Returns all edges in the `StateGraph`, in the order they were added.
+    /// 
+    pub fn edges(&self) -> &[GraphEdge] {
+        &self.edges
+    }
+
+    /// Returns the nested `StateGraph` of the composite state with the specified `name`, if any.
+    /// 
+    pub fn substates(&self, name : &str) -> Option<&StateGraph> {
+        self.substates.iter().find(|(existing, _)| existing == name).map(|(_, substates)| substates)
+    }
+
+    /// Returns the name of the initial state, if any.
+    /// 
+    pub fn initial(&self) -> Option<&str> {
+        self.initial.as_deref()
+    }
+
+    /// Returns the name of the current state, if any.
+    /// 
+    pub fn current(&self) -> Option<&str> {
+        self.current.as_deref()
+    }
+
+    /// Exports the `StateGraph` as a Graphviz DOT `digraph`, with one node per state and one labeled edge per
+    /// transition. The current state, if any, is highlighted with a filled background, and the initial state, if any,
+    /// is pointed to by a start marker. Composite states are drawn as ordinary nodes, without their nested states.
+    /// 
+    /// # Usage
+    /// ```
+    /// use mode::*;
+    /// 
+    /// #[derive(Copy, Clone, Debug)]
+    /// enum PrintOp { Clear, Print }
+    /// 
+    /// #[derive(Copy, Clone, Debug)]
+    /// enum ShiftOp { Left, Right }
+    /// 
+    /// #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+    /// enum State { A, B, C, D, E, H }
+    /// 
+    /// fn write(tape : &mut u16, print_op : PrintOp, shift_op : ShiftOp) {
+    ///     // ...
+    /// }
+    /// 
+    /// use PrintOp::*;
+    /// use ShiftOp::*;
+    /// 
+    /// transitions! {
+    ///     struct BusyBeaver for State {
+    ///         states { A, B, C, D, E, H }
+    ///         fn step(bit : u16, tape : &mut u16);
+    /// 
+    ///         A + 0 => H,
+    ///         A + 1 => B / write(tape, Clear, Right),
+    ///         B + 0 => C / write(tape, Clear, Right),
+    ///         B + 1 => B / write(tape, Print, Right),
+    ///         C + 0 => D / write(tape, Print, Left),
+    ///         C + 1 => C / write(tape, Print, Right),
+    ///         D + 0 => E / write(tape, Clear, Left),
+    ///         D + 1 => D / write(tape, Print, Left),
+    ///         E + 0 => A / write(tape, Print, Right),
+    ///         E + 1 => E / write(tape, Print, Left),
+    ///     }
+    /// }
+    /// 
+    /// let mut graph = StateGraph::from_table::<BusyBeaver>();
+    /// graph.set_current("C");
+    /// 
+    /// assert_eq!(graph.to_dot(), r#"digraph {
+    ///     "A";
+    ///     "B";
+    ///     "C" [style=filled, fillcolor=lightgrey];
+    ///     "D";
+    ///     "E";
+    ///     "H";
+    ///     "A" -> "H" [label="0"];
+    ///     "A" -> "B" [label="1 / write(tape, Clear, Right)"];
+    ///     "B" -> "C" [label="0 / write(tape, Clear, Right)"];
+    ///     "B" -> "B" [label="1 / write(tape, Print, Right)"];
+    ///     "C" -> "D" [label="0 / write(tape, Print, Left)"];
+    ///     "C" -> "C" [label="1 / write(tape, Print, Right)"];
+    ///     "D" -> "E" [label="0 / write(tape, Clear, Left)"];
+    ///     "D" -> "D" [label="1 / write(tape, Print, Left)"];
+    ///     "E" -> "A" [label="0 / write(tape, Print, Right)"];
+    ///     "E" -> "E" [label="1 / write(tape, Print, Left)"];
+    /// }
+    /// "#);
+    /// ```
+    /// 
+    /// ## Initial state
+    /// The start marker is drawn as a point-shaped pseudo-node, whose ID is chosen so that it never collides with the
+    /// name of a state.
+    /// ```
+    /// use mode::StateGraph;
+    /// 
+    /// let mut graph = StateGraph::new();
+    /// graph.add_state("__initial");
+    /// graph.set_initial("__initial");
+    /// 
+    /// assert_eq!(graph.to_dot(), r#"digraph {
+    ///     __initial_ [shape=point];
+    ///     __initial_ -> "__initial";
+    ///     "__initial";
+    /// }
+    /// "#);
+    /// ```
+    /// 
+    pub fn to_dot(&self) -> String {
+        Dot(self).to_string()
+    }
+
+    /// Exports the `StateGraph` as a Mermaid `stateDiagram-v2`, with one labeled transition per edge and nested
+    /// states drawn inside their composite states. The initial state of each `StateGraph`, if any, is pointed to by a
+    /// `[*]` start marker, and the current state, if any, is highlighted via a `current` class.
+    /// 
+    /// # Usage
+    /// ```
+    /// use mode::StateGraph;
+    /// 
+    /// let mut combat = StateGraph::new();
+    /// combat.add_state("Approach");
+    /// combat.add_state("Attack");
+    /// combat.add_edge("Approach", "Attack", Some("Tick"));
+    /// combat.set_initial("Approach");
+    /// 
+    /// let mut ai = StateGraph::with_name("Ai");
+    /// ai.add_state("Patrol");
+    /// ai.add_composite_state("Combat", combat);
+    /// ai.add_edge("Patrol", "Combat", Some("EnemySpotted"));
+    /// ai.add_edge("Combat", "Patrol", Some("EnemyLost"));
+    /// ai.set_initial("Patrol");
+    /// ai.set_current("Combat");
+    /// 
+    /// assert_eq!(ai.to_mermaid(), "\
+    /// ---
+    /// title: Ai
+    /// ---
+    /// stateDiagram-v2
+    ///     [*] --> Patrol
+    ///     Patrol
+    ///     state Combat {
+    ///         [*] --> Approach
+    ///         Approach
+    ///         Attack
+    ///         Approach --> Attack : Tick
+    ///     }
+    ///     Patrol --> Combat : EnemySpotted
+    ///     Combat --> Patrol : EnemyLost
+    ///     classDef current fill:lightgrey
+    ///     class Combat current
+    /// ");
+    /// ```
+    /// 
+    /// State names that aren't identifiers are declared with a quoted label and referred to by an escaped ID:
+    /// ```
+    /// use mode::StateGraph;
+    /// 
+    /// let mut graph = StateGraph::new();
+    /// graph.add_state("Idle");
+    /// graph.add_state("Turn-Left");
+    /// graph.add_edge("Idle", "Turn-Left", Some("key: \"a\"; repeat"));
+    /// 
+    /// assert_eq!(graph.to_mermaid(), "\
+    /// stateDiagram-v2
+    ///     Idle
+    ///     state \"Turn-Left\" as __Turn_2d_Left
+    ///     Idle --> __Turn_2d_Left : key: #quot;a#quot;#59; repeat
+    /// ");
+    /// 
+    /// assert_eq!(graph.to_plantuml(), "\
+    /// @startuml
+    /// state Idle
+    /// state \"Turn-Left\" as __Turn_2d_Left
+    /// Idle --> __Turn_2d_Left : key: <U+0022>a<U+0022>; repeat
+    /// @enduml
+    /// ");
+    /// ```
+    /// 
+    pub fn to_mermaid(&self) -> String {
+        Mermaid(self).to_string()
+    }
+
+    /// Exports the `StateGraph` as a PlantUML state diagram, with one labeled transition per edge and nested states
+    /// drawn inside their composite states. The initial state of each `StateGraph`, if any, is pointed to by a `[*]`
+    /// start marker, and the current state, if any, is highlighted with a filled background.
+    /// 
+    /// # Usage
+    /// ```
+    /// use mode::StateGraph;
+    /// 
+    /// let mut combat = StateGraph::new();
+    /// combat.add_state("Approach");
+    /// combat.add_state("Attack");
+    /// combat.add_edge("Approach", "Attack", Some("Tick"));
+    /// combat.set_initial("Approach");
+    /// combat.set_current("Attack");
+    /// 
+    /// let mut ai = StateGraph::with_name("Ai");
+    /// ai.add_state("Patrol");
+    /// ai.add_composite_state("Combat", combat);
+    /// ai.add_edge("Patrol", "Combat", Some("EnemySpotted"));
+    /// ai.add_edge("Combat", "Patrol", Some("EnemyLost"));
+    /// ai.set_initial("Patrol");
+    /// ai.set_current("Combat");
+    /// 
+    /// assert_eq!(ai.to_plantuml(), "\
+    /// @startuml
+    /// title Ai
+    /// [*] --> Patrol
+    /// state Patrol
+    /// state Combat #lightgrey {
+    ///     [*] --> Approach
+    ///     state Approach
+    ///     state Attack #lightgrey
+    ///     Approach --> Attack : Tick
+    /// }
+    /// Patrol --> Combat : EnemySpotted
+    /// Combat --> Patrol : EnemyLost
+    /// @enduml
+    /// ");
+    /// ```
+    /// 
+    pub fn to_plantuml(&self) -> String {
+        PlantUml(self).to_string()
+    }
+
+    fn is_current(&self, state : &str) -> bool {
+        self.current.as_deref() == Some(state)
+    }
+
+    /// Returns `prefix`, followed by as many underscores as it takes for it not to collide with the name of any state
+    /// in the `StateGraph`, so that it can be used as the ID of a pseudo-node in DOT, where every name is an ID.
+    /// 
+    fn unused_node_id(&self, prefix : &str) -> String {
+        let mut id = prefix.to_owned();
+        while self.states.iter()
+            .chain(self.edges.iter().flat_map(|edge| [&edge.from, &edge.to]))
+            .chain(&self.initial)
+            .any(|state| *state == id)
+        {
+            id.push('_');
+        }
+        id
+    }
+}
+
+struct Dot<'a>(&'a StateGraph);
+
+impl<'a> fmt::Display for Dot<'a> {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        let graph = self.0;
+
+        match &graph.name {
+            Some(name) => writeln!(f, "digraph {} {{", DotString(name))?,
+            None => writeln!(f, "digraph {{")?,
+        }
+
+        if let Some(initial) = &graph.initial {
+            let marker = graph.unused_node_id("__initial");
+            writeln!(f, "    {} [shape=point];", marker)?;
+            writeln!(f, "    {} -> {};", marker, DotString(initial))?;
+        }
+
+        for state in &graph.states {
+            if graph.is_current(state) {
+                writeln!(f, "    {} [style=filled, fillcolor=lightgrey];", DotString(state))?;
+            }
+            else {
+                writeln!(f, "    {};", DotString(state))?;
+            }
+        }
+
+        for edge in &graph.edges {
+            write!(f, "    {} -> {}", DotString(&edge.from), DotString(&edge.to))?;
+            if let Some(label) = &edge.label {
+                write!(f, " [label={}]", DotString(label))?;
+            }
+            writeln!(f, ";")?;
+        }
+
+        writeln!(f, "}}")
+    }
+}
+
+struct DotString<'a>(&'a str);
+
+impl<'a> fmt::Display for DotString<'a> {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "\"")?;
+        for c in self.0.chars() {
+            match c {
+                '"' | '\\' => write!(f, "\\{}", c)?,
+                '\n' => write!(f, "\\n")?,
+                _ => write!(f, "{}", c)?,
+            }
+        }
+        write!(f, "\"")
+    }
+}
+
+// Mermaid and PlantUML only accept identifiers as state IDs, so a state whose name isn't a plain identifier is declared
+// with its name as a quoted label, under an ID where every character other than an ASCII letter or digit is escaped as
+// `_<hex>_`. Escaped IDs start with `__`, so they can never collide with the name of another state.
+struct StateId<'a>(&'a str);
+
+impl<'a> StateId<'a> {
+    fn is_plain(&self) -> bool {
+        let mut chars = self.0.chars();
+        matches!(chars.next(), Some(c) if c.is_ascii_alphabetic())
+            && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+    }
+}
+
+impl<'a> fmt::Display for StateId<'a> {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        if self.is_plain() {
+            return write!(f, "{}", self.0);
+        }
+
+        write!(f, "__")?;
+        for c in self.0.chars() {
+            if c.is_ascii_alphanumeric() {
+                write!(f, "{}", c)?;
+            }
+            else {
+                write!(f, "_{:x}_", c as u32)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Text in a Mermaid label or quoted state name. Newlines, semicolons, and quotes would otherwise end the statement or
+// the string early, so they are replaced with spaces or written as entity codes, along with `#` itself.
+struct MermaidText<'a>(&'a str);
+
+impl<'a> fmt::Display for MermaidText<'a> {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        for c in self.0.chars() {
+            match c {
+                '#' => write!(f, "#35;")?,
+                ';' => write!(f, "#59;")?,
+                '"' => write!(f, "#quot;")?,
+                '\n' => write!(f, " ")?,
+                _ => write!(f, "{}", c)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+// The title of a Mermaid diagram, which is written into YAML front matter. Anything that isn't a plain identifier is
+// written as a double-quoted YAML string.
+struct YamlString<'a>(&'a str);
+
+impl<'a> fmt::Display for YamlString<'a> {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        if StateId(self.0).is_plain() {
+            return write!(f, "{}", self.0);
+        }
+
+        write!(f, "\"")?;
+        for c in self.0.chars() {
+            match c {
+                '"' | '\\' => write!(f, "\\{}", c)?,
+                '\n' => write!(f, "\\n")?,
+                _ => write!(f, "{}", c)?,
+            }
+        }
+        write!(f, "\"")
+    }
+}
+
+// Text in a PlantUML label, title, or quoted state name. Newlines are written as `\n`, and quotes as a Unicode escape,
+// so that neither can end the statement or the string early.
+struct PlantUmlText<'a>(&'a str);
+
+impl<'a> fmt::Display for PlantUmlText<'a> {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        for c in self.0.chars() {
+            match c {
+                '"' => write!(f, "<U+0022>")?,
+                '\n' => write!(f, "\\n")?,
+                _ => write!(f, "{}", c)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Mermaid<'a>(&'a StateGraph);
+
+impl<'a> Mermaid<'a> {
+    fn write_states(f : &mut fmt::Formatter, graph : &StateGraph, depth : usize) -> fmt::Result {
+        let indent = "    ".repeat(depth);
+
+        if let Some(initial) = &graph.initial {
+            writeln!(f, "{}[*] --> {}", indent, StateId(initial))?;
+        }
+
+        for state in &graph.states {
+            let id = StateId(state);
+            if !id.is_plain() {
+                writeln!(f, "{}state \"{}\" as {}", indent, MermaidText(state), id)?;
+            }
+
+            match graph.substates(state) {
+                Some(substates) => {
+                    writeln!(f, "{}state {} {{", indent, id)?;
+                    Self::write_states(f, substates, depth + 1)?;
+                    writeln!(f, "{}}}", indent)?;
+                },
+                None if id.is_plain() => writeln!(f, "{}{}", indent, id)?,
+                None => { },
+            }
+        }
+
+        for edge in &graph.edges {
+            write!(f, "{}{} --> {}", indent, StateId(&edge.from), StateId(&edge.to))?;
+            if let Some(label) = &edge.label {
+                write!(f, " : {}", MermaidText(label))?;
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_current(f : &mut fmt::Formatter, graph : &StateGraph) -> fmt::Result {
+        if let Some(current) = &graph.current {
+            writeln!(f, "    class {} current", StateId(current))?;
+        }
+        for (_, substates) in &graph.substates {
+            Self::write_current(f, substates)?;
+        }
+        Ok(())
+    }
+
+    fn has_current(graph : &StateGraph) -> bool {
+        graph.current.is_some() || graph.substates.iter().any(|(_, substates)| Self::has_current(substates))
+    }
+}
+
+impl<'a> fmt::Display for Mermaid<'a> {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        let graph = self.0;
+
+        if let Some(name) = &graph.name {
+            writeln!(f, "---\ntitle: {}\n---", YamlString(name))?;
+        }
+
+        writeln!(f, "stateDiagram-v2")?;
+        Self::write_states(f, graph, 1)?;
+
+        if Self::has_current(graph) {
+            writeln!(f, "    classDef current fill:lightgrey")?;
+            Self::write_current(f, graph)?;
+        }
+
+        Ok(())
+    }
+}
+
+struct PlantUml<'a>(&'a StateGraph);
+
+impl<'a> PlantUml<'a> {
+    fn write_states(f : &mut fmt::Formatter, graph : &StateGraph, depth : usize) -> fmt::Result {
+        let indent = "    ".repeat(depth);
+
+        if let Some(initial) = &graph.initial {
+            writeln!(f, "{}[*] --> {}", indent, StateId(initial))?;
+        }
+
+        for state in &graph.states {
+            let id = StateId(state);
+            if id.is_plain() {
+                write!(f, "{}state {}", indent, id)?;
+            }
+            else {
+                write!(f, "{}state \"{}\" as {}", indent, PlantUmlText(state), id)?;
+            }
+            if graph.is_current(state) {
+                write!(f, " #lightgrey")?;
+            }
+            match graph.substates(state) {
+                Some(substates) => {
+                    writeln!(f, " {{")?;
+                    Self::write_states(f, substates, depth + 1)?;
+                    writeln!(f, "{}}}", indent)?;
+                },
+                None => writeln!(f)?,
+            }
+        }
+
+        for edge in &graph.edges {
+            write!(f, "{}{} --> {}", indent, StateId(&edge.from), StateId(&edge.to))?;
+            if let Some(label) = &edge.label {
+                write!(f, " : {}", PlantUmlText(label))?;
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> fmt::Display for PlantUml<'a> {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        let graph = self.0;
+
+        writeln!(f, "@startuml")?;
+        if let Some(name) = &graph.name {
+            writeln!(f, "title {}", PlantUmlText(name))?;
+        }
+        Self::write_states(f, graph, 0)?;
+        writeln!(f, "@enduml")
+    }
+}
+
+/// Trait that allows the states and edges of a `Family` to be inspected at runtime, e.g. in order to generate a
+/// diagram of the state machine via [`Automaton::state_graph()`](struct.Automaton.html#method.state_graph).
+/// 
+/// The current `Mode` is matched to a state in the `StateGraph` by its
+/// [`NamedMode::mode_name()`](trait.NamedMode.html#method.mode_name), so `F::Base` must implement
+/// [`NamedMode`](trait.NamedMode.html). For a `Family` whose transitions are defined via the
+/// [`transitions!`](macro.transitions.html) macro, both traits can be implemented in terms of the generated
+/// [`TransitionTable`](trait.TransitionTable.html).
+/// 
+/// # Usage
+/// ```
+/// use mode::*;
+/// 
+/// struct LightFamily;
+/// impl Family for LightFamily {
+///     type Base = Light;
+///     type Mode = Light;
+/// }
+/// 
+/// #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+/// enum Light { Off, On }
+/// impl Mode for Light { type Family = LightFamily; }
+/// 
+/// transitions! {
+///     struct LightTable for Light {
+///         states { Off, On }
+///         fn step(input : ());
+/// 
+///         Off + () => On,
+///         On + () => Off,
+///     }
+/// }
+/// 
+/// impl Introspect for LightFamily {
+///     fn state_graph() -> StateGraph { StateGraph::from_table::<LightTable>() }
+/// }
+/// 
+/// impl NamedMode for Light {
+///     fn mode_name(&self) -> &'static str { LightTable::state_name(self) }
+/// }
+/// 
+/// let mut automaton = LightFamily::automaton_with_mode(Light::Off);
+/// Automaton::next_with_result(&mut automaton, |light| LightTable::step(light, ()));
+/// 
+/// let graph = Automaton::state_graph(&automaton);
+/// assert_eq!(graph.current(), Some("On"));
+/// ```
+/// 
+pub trait Introspect : Family {
+    /// Returns a description of all states and edges in the `Family`.
+    /// 
+    fn state_graph() -> StateGraph;
+}
+
+impl<F> Automaton<F>
+    where
+        F : Introspect + ?Sized,
+        F::Mode : Borrow<F::Base>,
+        F::Base : NamedMode,
+{
+    /// Returns a `StateGraph` describing all states and edges in `F`, with the current `Mode` of the `Automaton` marked
+    /// as current. If the `Automaton` is poisoned, no state will be marked as current.
+    /// 
+    /// See [`Introspect`](trait.Introspect.html) for more details.
+    /// 
+    pub fn state_graph(automaton : &Self) -> StateGraph {
+        let mut graph = F::state_graph();
+        if let Ok(mode) = automaton.try_borrow_mode() {
+            graph.set_current(mode.mode_name());
+        }
+        graph
+    }
+}
+
+impl<F> Automaton<F>
+    where
+        F : Introspect + ?Sized,
+        F::Mode : Borrow<F::Base> + Default,
+        F::Base : NamedMode,
+{
+    /// Returns a `StateGraph` describing all states and edges in `F`, with the `Mode` that
+    /// [`Automaton::new()`](struct.Automaton.html#method.new) would start in marked as the initial state. No state will
+    /// be marked as current.
+    /// 
+    /// # Usage
+    /// ```
+    /// use mode::*;
+    /// 
+    /// struct LightFamily;
+    /// impl Family for LightFamily {
+    ///     type Base = Light;
+    ///     type Mode = Light;
+    /// }
+    /// 
+    /// #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+    /// enum Light { Off, On }
+    /// impl Mode for Light { type Family = LightFamily; }
+    /// impl Default for Light {
+    ///     fn default() -> Self { Light::Off }
+    /// }
+    /// 
+    /// impl Introspect for LightFamily {
+    ///     fn state_graph() -> StateGraph {
+    ///         let mut graph = StateGraph::new();
+    ///         graph.add_edge("Off", "On", Some("flip"));
+    ///         graph.add_edge("On", "Off", Some("flip"));
+    ///         graph
+    ///     }
+    /// }
+    /// 
+    /// impl NamedMode for Light {
+    ///     fn mode_name(&self) -> &'static str {
+    ///         match self { Light::Off => "Off", Light::On => "On" }
+    ///     }
+    /// }
+    /// 
+    /// assert_eq!(Automaton::<LightFamily>::initial_state_graph().initial(), Some("Off"));
+    /// ```
+    /// 
+    pub fn initial_state_graph() -> StateGraph {
+        let mut graph = F::state_graph();
+        graph.set_initial(F::Mode::default().borrow().mode_name());
+        graph
+    }
+}
@@ -0,0 +1,182 @@
+// Copyright 2019 Andrew Thomas Christensen
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the
+// MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+use crate::{Automaton, Family};
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+use core::{any::Any, borrow::{Borrow, BorrowMut}};
+
+/// Trait that allows the current `Mode` of an `Automaton` to be downcast to its concrete type, via
+/// [`Automaton::is()`](struct.Automaton.html#method.is),
+/// [`Automaton::downcast_ref()`](struct.Automaton.html#method.downcast_ref),
+/// [`Automaton::downcast_mut()`](struct.Automaton.html#method.downcast_mut), and
+/// [`Automaton::next_if()`](struct.Automaton.html#method.next_if).
+/// 
+/// `AnyMode` is implemented for every type that implements `Any`, i.e. every `'static` type, so there is nothing to
+/// implement. For a `Family` where `Base` is a `dyn Trait`, simply add `AnyMode` as a supertrait of the `Base` trait.
+/// This makes it possible to access data that is specific to a single `Mode` from outside the `Automaton`, without
+/// adding functions like `is_working()` or `hours_worked()` to the `Base` trait for every `Mode`.
+/// 
+/// **NOTE:** Since `Automaton` is itself `Any`, calling `automaton.as_any()` will **not** dispatch to the current
+/// `Mode`. Use the associated functions on `Automaton` instead.
+/// 
+/// # Usage
+/// ```
+/// # #[cfg(feature = "alloc")] {
+/// use mode::*;
+/// 
+/// struct ActivityFamily;
+/// impl Family for ActivityFamily {
+///     type Base = dyn Activity;
+///     type Mode = Box<dyn Activity>;
+/// }
+/// 
+/// trait Activity : Mode<Family = ActivityFamily> + AnyMode {
+///     fn update(self : Box<Self>) -> Box<dyn Activity>;
+/// }
+/// 
+/// struct Working { hours_worked : u32 }
+/// impl Mode for Working { type Family = ActivityFamily; }
+/// impl Activity for Working {
+///     fn update(mut self : Box<Self>) -> Box<dyn Activity> {
+///         self.hours_worked += 1;
+///         if self.hours_worked >= 8 { Box::new(Sleeping) } else { self }
+///     }
+/// }
+/// 
+/// struct Sleeping;
+/// impl Mode for Sleeping { type Family = ActivityFamily; }
+/// impl Activity for Sleeping {
+///     fn update(self : Box<Self>) -> Box<dyn Activity> { self }
+/// }
+/// 
+/// let mut automaton = ActivityFamily::automaton_with_mode(Box::new(Working { hours_worked: 0 }));
+/// assert!(Automaton::is::<Working>(&automaton));
+/// 
+/// // Data specific to the current Mode can be accessed by downcasting it to its concrete type.
+/// Automaton::next(&mut automaton, |current_mode| current_mode.update());
+/// assert_eq!(Automaton::downcast_ref::<Working>(&automaton).unwrap().hours_worked, 1);
+/// Automaton::downcast_mut::<Working>(&mut automaton).unwrap().hours_worked = 7;
+/// 
+/// // next_if() only calls the transition function if the current Mode is of the specified type.
+/// assert!(!Automaton::next_if(&mut automaton, |sleeping : Box<Sleeping>| sleeping));
+/// assert!(Automaton::next_if(&mut automaton, |working : Box<Working>| working.update()));
+/// assert!(Automaton::is::<Sleeping>(&automaton));
+/// assert!(Automaton::downcast_ref::<Working>(&automaton).is_none());
+/// # }
+/// ```
+/// 
+pub trait AnyMode {
+    /// Returns a reference to this `Mode` as a `&dyn Any`.
+    /// 
+    fn as_any(&self) -> &dyn Any;
+
+    /// Returns a mutable reference to this `Mode` as a `&mut dyn Any`.
+    /// 
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    /// Converts a boxed `Mode` into a `Box<dyn Any>`. This requires the `alloc` feature.
+    /// 
+    #[cfg(feature = "alloc")]
+    fn into_any(self : Box<Self>) -> Box<dyn Any>;
+}
+
+impl<T> AnyMode for T
+    where T : Any
+{
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    #[cfg(feature = "alloc")]
+    fn into_any(self : Box<Self>) -> Box<dyn Any> {
+        self
+    }
+}
+
+impl<F> Automaton<F>
+    where
+        F : Family + ?Sized,
+        F::Mode : Borrow<F::Base>,
+        F::Base : AnyMode,
+{
+    /// Returns whether the current `Mode` is of type `T`.
+    /// 
+    /// **NOTE:** Since `Automaton` implements `Deref`, this is an associated function rather than a method, so that it
+    /// doesn't shadow any function with the same name on `F::Base`. Call it as `Automaton::is::<T>(&automaton)`.
+    /// 
+    /// See [`AnyMode`](trait.AnyMode.html) for more details.
+    /// 
+    pub fn is<T>(automaton : &Self) -> bool
+        where T : Any
+    {
+        automaton.borrow_mode().as_any().is::<T>()
+    }
+
+    /// Returns a reference to the current `Mode` as a `&T`, or `None` if the current `Mode` is not of type `T`.
+    /// 
+    /// See [`AnyMode`](trait.AnyMode.html) for more details.
+    /// 
+    pub fn downcast_ref<T>(automaton : &Self) -> Option<&T>
+        where T : Any
+    {
+        automaton.borrow_mode().as_any().downcast_ref::<T>()
+    }
+}
+
+impl<F> Automaton<F>
+    where
+        F : Family + ?Sized,
+        F::Mode : BorrowMut<F::Base>,
+        F::Base : AnyMode,
+{
+    /// Returns a mutable reference to the current `Mode` as a `&mut T`, or `None` if the current `Mode` is not of type
+    /// `T`.
+    /// 
+    /// See [`AnyMode`](trait.AnyMode.html) for more details.
+    /// 
+    pub fn downcast_mut<T>(automaton : &mut Self) -> Option<&mut T>
+        where T : Any
+    {
+        automaton.borrow_mode_mut().as_any_mut().downcast_mut::<T>()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<F> Automaton<F>
+    where
+        F : Family<Mode = Box<<F as Family>::Base>> + ?Sized,
+        F::Base : AnyMode,
+{
+    /// If the current `Mode` is of type `T`, calls `transition_fn` on it as a `Box<T>` and swaps in whatever `Mode` it
+    /// returns, just like [`next()`](struct.Automaton.html#method.next), returning `true`. Otherwise, `transition_fn`
+    /// is **not** called, the current `Mode` remains active, and this returns `false`.
+    /// 
+    /// This requires the `alloc` feature, and a `Family` where `Mode` is `Box<Base>`.
+    /// 
+    /// See [`AnyMode`](trait.AnyMode.html) for more details.
+    /// 
+    pub fn next_if<T, TransitionFn>(automaton : &mut Self, transition_fn : TransitionFn) -> bool
+        where
+            T : Any,
+            TransitionFn : FnOnce(Box<T>) -> Box<F::Base>,
+    {
+        if !Self::is::<T>(automaton) {
+            return false;
+        }
+
+        Self::next(automaton, |current_mode| {
+            let current_mode = <F::Base as AnyMode>::into_any(current_mode).downcast::<T>()
+                .unwrap_or_else(|_| unreachable!("The current Mode was checked to be of type T!"));
+            transition_fn(current_mode)
+        });
+        true
+    }
+}
@@ -0,0 +1,163 @@
+// Copyright 2019 Andrew Thomas Christensen
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the
+// MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+use crate::{Automaton, Family, Mode};
+
+/// Describes what the current `Mode` did with an event. This is returned from
+/// [`HandlesEvent::on_event()`](trait.HandlesEvent.html#tymethod.on_event),
+/// [`HierarchicalMode::handle_event()`](trait.HierarchicalMode.html#tymethod.handle_event), and the other event
+/// handling traits in this crate, with `M` being the `Mode` to switch to.
+/// 
+/// Once the `Automaton` has performed the switch, the `Response` is handed back to the caller of functions like
+/// [`Automaton::handle()`](struct.Automaton.html#method.handle) as a `Response<()>`, where `Response::Switch(())` means
+/// that a different `Mode` was swapped in.
+/// 
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Response<M = ()> {
+    /// The event was handled, and the current `Mode` should remain active.
+    /// 
+    Handled,
+
+    /// The event was handled, and the current `Mode` should be swapped out for the specified `Mode`.
+    /// 
+    Switch(M),
+
+    /// The event was not handled by the current `Mode`, which should remain active. In a hierarchical state machine,
+    /// the event bubbles up to the parent `Mode`, if any.
+    /// 
+    Unhandled,
+}
+
+impl<M> Response<M> {
+    /// Returns `true` if the event was handled, i.e. this is either `Response::Handled` or `Response::Switch`.
+    /// 
+    pub fn is_handled(&self) -> bool {
+        !matches!(self, Response::Unhandled)
+    }
+
+    // Returns the Mode to swap in, which is `current_mode` unless this is `Response::Switch`, along with the Response
+    // that should be handed back to the caller.
+    pub(crate) fn swap(self, current_mode : M) -> (M, Response) {
+        match self {
+            Response::Handled => (current_mode, Response::Handled),
+            Response::Switch(next_mode) => (next_mode, Response::Switch(())),
+            Response::Unhandled => (current_mode, Response::Unhandled),
+        }
+    }
+}
+
+/// Trait for a `Mode` that reacts to events of type `E`. This provides an opt-in, event-driven alternative to passing
+/// closures into `Automaton::next()`, allowing every transition in the state machine to be triggered in the same way,
+/// via [`Automaton::handle()`](struct.Automaton.html#method.handle).
+/// 
+/// Like [`HierarchicalMode::handle_event()`](trait.HierarchicalMode.html#tymethod.handle_event), `on_event()` returns a
+/// [`Response`](enum.Response.html) that tells the `Automaton` whether to swap in a different `Mode`. Since the next
+/// `Mode` is created from `&mut self`, `HandlesEvent` is implemented on the `Mode` type of the `Family`, e.g.
+/// `Box<dyn Trait>`, rather than on the `Base` type, so that `Response::Switch` can hold any `Mode` in the `Family`. A
+/// `Mode` can implement `HandlesEvent` for as many different event types as it needs.
+/// 
+/// # Usage
+/// ```
+/// use mode::*;
+/// 
+/// struct TurnstileFamily;
+/// impl Family for TurnstileFamily {
+///     type Base = Turnstile;
+///     type Mode = Turnstile;
+/// }
+/// 
+/// #[derive(Debug, Eq, PartialEq)]
+/// enum Turnstile { Locked, Unlocked }
+/// impl Mode for Turnstile { type Family = TurnstileFamily; }
+/// 
+/// enum Input { Coin, Push }
+/// 
+/// impl HandlesEvent<Input> for Turnstile {
+///     fn on_event(&mut self, event : Input) -> Response<Self> {
+///         match (self, event) {
+///             (Turnstile::Locked, Input::Coin) => Response::Switch(Turnstile::Unlocked),
+///             (Turnstile::Unlocked, Input::Push) => Response::Switch(Turnstile::Locked),
+///             (Turnstile::Unlocked, Input::Coin) => Response::Handled, // Thanks!
+///             _ => Response::Unhandled,
+///         }
+///     }
+/// }
+/// 
+/// let mut automaton = TurnstileFamily::automaton_with_mode(Turnstile::Locked);
+/// assert_eq!(Automaton::handle(&mut automaton, Input::Push), Response::Unhandled);
+/// assert_eq!(Automaton::handle(&mut automaton, Input::Coin), Response::Switch(()));
+/// assert_eq!(Automaton::handle(&mut automaton, Input::Coin), Response::Handled);
+/// assert_eq!(*automaton, Turnstile::Unlocked);
+/// ```
+/// 
+/// ## A `Family` where `Base` is a `dyn Trait`
+/// Since `HandlesEvent` is implemented on the `Mode` type, e.g. `Box<dyn Trait>`, each concrete `Mode` can handle
+/// events in its own way by delegating to a function on the `Base` trait:
+/// ```
+/// # #[cfg(feature = "alloc")] {
+/// use mode::*;
+/// 
+/// struct LightFamily;
+/// impl Family for LightFamily {
+///     type Base = dyn Light;
+///     type Mode = Box<dyn Light>;
+/// }
+/// 
+/// struct Toggle;
+/// 
+/// trait Light : Mode<Family = LightFamily> {
+///     fn is_on(&self) -> bool;
+///     fn on_toggle(&self) -> Box<dyn Light>;
+/// }
+/// 
+/// impl HandlesEvent<Toggle> for Box<dyn Light> {
+///     fn on_event(&mut self, _ : Toggle) -> Response<Self> {
+///         Response::Switch(self.on_toggle())
+///     }
+/// }
+/// 
+/// struct On;
+/// impl Mode for On { type Family = LightFamily; }
+/// impl Light for On {
+///     fn is_on(&self) -> bool { true }
+///     fn on_toggle(&self) -> Box<dyn Light> { Box::new(Off) }
+/// }
+/// 
+/// struct Off;
+/// impl Mode for Off { type Family = LightFamily; }
+/// impl Light for Off {
+///     fn is_on(&self) -> bool { false }
+///     fn on_toggle(&self) -> Box<dyn Light> { Box::new(On) }
+/// }
+/// 
+/// let mut automaton = LightFamily::automaton_with_mode(Box::new(Off));
+/// Automaton::handle(&mut automaton, Toggle);
+/// assert!(automaton.is_on());
+/// # }
+/// ```
+/// 
+pub trait HandlesEvent<E> : Mode + Sized {
+    /// Called on the current `Mode` when an `event` is passed into `Automaton::handle()`. Returns a `Response`
+    /// indicating whether the event was handled, and whether the current `Mode` should be swapped out as a result.
+    /// 
+    fn on_event(&mut self, event : E) -> Response<Self>;
+}
+
+impl<F> Automaton<F>
+    where F : Family + ?Sized
+{
+    /// Passes `event` into the current `Mode` via [`HandlesEvent::on_event()`](trait.HandlesEvent.html), swapping in
+    /// the `Mode` from `Response::Switch`, if any, and returning the `Response`. This goes through the same transition
+    /// machinery as [`Automaton::next()`](struct.Automaton.html#method.next).
+    /// 
+    /// See [`HandlesEvent`](trait.HandlesEvent.html) for more details.
+    /// 
+    pub fn handle<E>(automaton : &mut Self, event : E) -> Response
+        where F::Mode : HandlesEvent<E>
+    {
+        Self::next_with_result(automaton, |mut current_mode| current_mode.on_event(event).swap(current_mode))
+    }
+}
@@ -58,6 +58,7 @@ use crate::{Automaton, Mode};
 /// 
 /// ## A `Family` where `Base` is a `dyn Trait`
 /// ```
+/// # #[cfg(feature = "alloc")] {
 /// use mode::{Mode, Family};
 /// 
 /// trait SomeTrait : Mode<Family = SomeFamily> {
@@ -70,6 +71,7 @@ use crate::{Automaton, Mode};
 ///     type Base = dyn SomeTrait; // All SomeFamily Modes will expose their SomeTrait interface via the Automaton.
 ///     type Mode = Box<dyn SomeTrait>; // The current Mode in the Automaton will be stored as a Box<dyn SomeTrait>.
 /// }
+/// # }
 /// ```
 /// 
 pub trait Family {
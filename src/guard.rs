@@ -0,0 +1,206 @@
+// Copyright 2019 Andrew Thomas Christensen
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the
+// MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+use crate::{Automaton, Family};
+use core::borrow::Borrow;
+
+/// The result of checking a [`Guard`](trait.Guard.html) against the current `Mode` of an `Automaton`. If the transition
+/// is refused, `Denied` carries a reason of type `R` explaining why, e.g. so that a UI can explain why a button is
+/// disabled.
+/// 
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum GuardResult<R> {
+    /// The guarded transition may be performed.
+    /// 
+    Allowed,
+
+    /// The guarded transition may not be performed, for the given reason.
+    /// 
+    Denied(R),
+}
+
+impl<R> GuardResult<R> {
+    /// Returns `Allowed` if `condition` is `true`, and `Denied(reason)` otherwise.
+    /// 
+    pub fn allowed_if(condition : bool, reason : R) -> Self {
+        if condition { GuardResult::Allowed } else { GuardResult::Denied(reason) }
+    }
+
+    /// Returns `true` if this is `GuardResult::Allowed`.
+    /// 
+    pub fn is_allowed(&self) -> bool {
+        match self {
+            GuardResult::Allowed => true,
+            GuardResult::Denied(_) => false,
+        }
+    }
+
+    /// Returns `true` if this is `GuardResult::Denied`.
+    /// 
+    pub fn is_denied(&self) -> bool {
+        !self.is_allowed()
+    }
+
+    /// Returns the reason the transition was denied, if any.
+    /// 
+    pub fn reason(&self) -> Option<&R> {
+        match self {
+            GuardResult::Allowed => None,
+            GuardResult::Denied(reason) => Some(reason),
+        }
+    }
+
+    /// Returns `other` if this is `Allowed`, or this `Denied` result otherwise. This can be used to combine several
+    /// conditions into one `GuardResult` that reports the first reason for denial.
+    /// 
+    pub fn and(self, other : Self) -> Self {
+        match self {
+            GuardResult::Allowed => other,
+            denied => denied,
+        }
+    }
+}
+
+impl<R> From<GuardResult<R>> for Result<(), R> {
+    fn from(result : GuardResult<R>) -> Self {
+        match result {
+            GuardResult::Allowed => Ok(()),
+            GuardResult::Denied(reason) => Err(reason),
+        }
+    }
+}
+
+/// Trait for a declarative condition that must hold for the current `Mode` of an `Automaton` in `Family` `F` before a
+/// transition may be performed. A `Guard` only inspects the current `Mode`, via an `&F::Base` reference, so it can be
+/// checked ahead of time via [`Automaton::check_guard()`](struct.Automaton.html#method.check_guard) without performing
+/// the transition, or checked and followed by the transition via
+/// [`Automaton::next_guarded()`](struct.Automaton.html#method.next_guarded).
+/// 
+/// `Guard` is implemented for any closure that takes an `&F::Base` and returns a `GuardResult`.
+/// 
+/// # Usage
+/// ```
+/// use mode::*;
+/// 
+/// struct ActivityFamily;
+/// impl Family for ActivityFamily {
+///     type Base = Activity;
+///     type Mode = Activity;
+/// }
+/// 
+/// #[derive(Debug, Eq, PartialEq)]
+/// enum Activity {
+///     Working { hours_worked : u32 },
+///     Eating { hours_worked : u32 },
+///     Sleeping,
+/// }
+/// 
+/// impl Mode for Activity { type Family = ActivityFamily; }
+/// 
+/// #[derive(Debug, Eq, PartialEq)]
+/// enum Denial { NotEating, ShiftOver { hours_worked : u32 } }
+/// 
+/// // You may only go from Eating to Working if hours_worked < 8.
+/// struct BackToWork;
+/// impl Guard<ActivityFamily> for BackToWork {
+///     type Reason = Denial;
+/// 
+///     fn check(&self, activity : &Activity) -> GuardResult<Denial> {
+///         match *activity {
+///             Activity::Eating { hours_worked } => {
+///                 GuardResult::allowed_if(hours_worked < 8, Denial::ShiftOver { hours_worked })
+///             },
+///             _ => GuardResult::Denied(Denial::NotEating),
+///         }
+///     }
+/// }
+/// 
+/// fn back_to_work(activity : Activity) -> Activity {
+///     match activity {
+///         Activity::Eating { hours_worked } => Activity::Working { hours_worked },
+///         activity => activity,
+///     }
+/// }
+/// 
+/// let mut automaton = ActivityFamily::automaton_with_mode(Activity::Eating { hours_worked: 8 });
+/// 
+/// // The UI can ask why the transition isn't allowed without attempting it.
+/// let result = Automaton::check_guard(&automaton, &BackToWork);
+/// assert_eq!(result, GuardResult::Denied(Denial::ShiftOver { hours_worked: 8 }));
+/// 
+/// // Attempting the transition reports the same reason, and leaves the current Mode in place.
+/// let result = Automaton::next_guarded(&mut automaton, &BackToWork, back_to_work);
+/// assert_eq!(result.reason(), Some(&Denial::ShiftOver { hours_worked: 8 }));
+/// assert_eq!(*automaton, Activity::Eating { hours_worked: 8 });
+/// 
+/// // Closures can be used as Guards, too.
+/// let mut automaton = ActivityFamily::automaton_with_mode(Activity::Eating { hours_worked: 4 });
+/// let is_eating = |activity : &Activity| {
+///     GuardResult::allowed_if(matches!(activity, Activity::Eating { .. }), Denial::NotEating)
+/// };
+/// assert!(Automaton::next_guarded(&mut automaton, &is_eating, back_to_work).is_allowed());
+/// assert_eq!(*automaton, Activity::Working { hours_worked: 4 });
+/// ```
+/// 
+pub trait Guard<F>
+    where F : Family + ?Sized
+{
+    /// The type of reason returned when the transition is denied.
+    /// 
+    type Reason;
+
+    /// Checks whether the transition may be performed from the `current` `Mode`.
+    /// 
+    fn check(&self, current : &F::Base) -> GuardResult<Self::Reason>;
+}
+
+impl<F, G, R> Guard<F> for G
+    where
+        F : Family + ?Sized,
+        G : Fn(&F::Base) -> GuardResult<R>,
+{
+    type Reason = R;
+
+    fn check(&self, current : &F::Base) -> GuardResult<R> {
+        self(current)
+    }
+}
+
+impl<F> Automaton<F>
+    where
+        F : Family + ?Sized,
+        F::Mode : Borrow<F::Base>,
+{
+    /// Checks `guard` against the current `Mode` **without** performing any transition, returning whether a transition
+    /// guarded by it would currently be allowed, and if not, why.
+    /// 
+    /// See [`Guard`](trait.Guard.html) for more details.
+    /// 
+    pub fn check_guard<G>(automaton : &Self, guard : &G) -> GuardResult<G::Reason>
+        where G : Guard<F> + ?Sized
+    {
+        guard.check(automaton.borrow_mode())
+    }
+
+    /// Checks `guard` against the current `Mode`. If the transition is `Allowed`, calls `transition_fn` on the current
+    /// `Mode` and swaps in whatever `Mode` it returns, just like [`next()`](struct.Automaton.html#method.next). If the
+    /// transition is `Denied`, `transition_fn` is **not** called and the current `Mode` remains active. Returns the
+    /// `GuardResult` either way.
+    /// 
+    /// See [`Guard`](trait.Guard.html) for more details.
+    /// 
+    pub fn next_guarded<G, T>(automaton : &mut Self, guard : &G, transition_fn : T) -> GuardResult<G::Reason>
+        where
+            G : Guard<F> + ?Sized,
+            T : FnOnce(F::Mode) -> F::Mode,
+    {
+        let result = guard.check(automaton.borrow_mode());
+        if result.is_allowed() {
+            Self::next(automaton, transition_fn);
+        }
+        result
+    }
+}
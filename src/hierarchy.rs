@@ -0,0 +1,282 @@
+// Copyright 2019 Andrew Thomas Christensen
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the
+// MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+use crate::{Automaton, Family, Mode, ModeLifecycle, Response};
+use core::borrow::BorrowMut;
+
+/// Trait for the `Base` type of a `Family` whose `Mode`s can be nested inside one another to form a hierarchical state
+/// machine that handles events of type `E`.
+/// 
+/// In a hierarchical state machine, a `Mode` in one `Family` (the parent) can own an `Automaton` over a different
+/// `Family` (the child). The parent exposes its child `Automaton` to the library by returning it from
+/// [`submachine()`](#method.submachine). Modes that don't have any children can simply leave `submachine()`
+/// unimplemented.
+/// 
+/// # Semantics
+/// When an event is passed into [`Automaton::dispatch()`](struct.Automaton.html#method.dispatch):
+/// 
+/// 1. The event is delegated to the **innermost** active `Mode` first, i.e. the current `Mode` of the child
+///    `Automaton` of the current `Mode`, and so on, all the way down.
+/// 2. If a `Mode` returns `Response::Unhandled`, the event bubbles **up** to its parent `Mode`, which gets a chance to
+///    handle it. If the outermost `Mode` doesn't handle the event either, `dispatch()` returns `false`.
+/// 3. If a `Mode` returns `Response::Switch`, the `Automaton` that owns it transitions to the new `Mode`. All `Mode`s
+///    nested inside the outgoing `Mode` are exited first, from the innermost `Mode` outward, followed by the outgoing
+///    `Mode` itself. Then the incoming `Mode` is entered, followed by all `Mode`s nested inside of it, from the
+///    outermost `Mode` inward.
+/// 
+/// Entering and exiting a `Mode` calls the `on_enter()` and `on_exit()` hooks from
+/// [`ModeLifecycle`](trait.ModeLifecycle.html), which is a supertrait of `HierarchicalMode`.
+/// 
+/// # Usage
+/// ```
+/// use mode::*;
+/// use std::sync::{Arc, Mutex};
+/// 
+/// type Log = Arc<Mutex<Vec<&'static str>>>;
+/// 
+/// enum Event { Tick, EnemySpotted, EnemyLost }
+/// 
+/// // The outer state machine.
+/// struct AiFamily;
+/// impl Family for AiFamily {
+///     type Base = Ai;
+///     type Mode = Ai;
+/// }
+/// 
+/// enum Ai {
+///     Patrol(Log),
+///     Combat(Log, Automaton<CombatFamily>),
+/// }
+/// 
+/// impl Mode for Ai { type Family = AiFamily; }
+/// 
+/// impl ModeLifecycle for Ai {
+///     fn on_enter(&mut self) {
+///         match self {
+///             Ai::Patrol(log) => log.lock().unwrap().push("enter Patrol"),
+///             Ai::Combat(log, _) => log.lock().unwrap().push("enter Combat"),
+///         }
+///     }
+///     fn on_exit(&mut self) {
+///         match self {
+///             Ai::Patrol(log) => log.lock().unwrap().push("exit Patrol"),
+///             Ai::Combat(log, _) => log.lock().unwrap().push("exit Combat"),
+///         }
+///     }
+/// }
+/// 
+/// impl HierarchicalMode<Event> for Ai {
+///     fn handle_event(&mut self, event : &Event) -> Response<Ai> {
+///         match (self, event) {
+///             (Ai::Patrol(log), Event::EnemySpotted) => {
+///                 let child = CombatFamily::automaton_with_mode(Combat::Approach(log.clone()));
+///                 Response::Switch(Ai::Combat(log.clone(), child))
+///             },
+///             (Ai::Combat(log, _), Event::EnemyLost) => Response::Switch(Ai::Patrol(log.clone())),
+///             _ => Response::Unhandled,
+///         }
+///     }
+/// 
+///     fn submachine(&mut self) -> Option<&mut dyn Submachine<Event>> {
+///         match self {
+///             Ai::Combat(_, child) => Some(child),
+///             _ => None,
+///         }
+///     }
+/// }
+/// 
+/// // The inner state machine, nested inside Ai::Combat.
+/// struct CombatFamily;
+/// impl Family for CombatFamily {
+///     type Base = Combat;
+///     type Mode = Combat;
+/// }
+/// 
+/// enum Combat {
+///     Approach(Log),
+///     Attack(Log),
+/// }
+/// 
+/// impl Mode for Combat { type Family = CombatFamily; }
+/// 
+/// impl ModeLifecycle for Combat {
+///     fn on_enter(&mut self) {
+///         match self {
+///             Combat::Approach(log) => log.lock().unwrap().push("enter Approach"),
+///             Combat::Attack(log) => log.lock().unwrap().push("enter Attack"),
+///         }
+///     }
+///     fn on_exit(&mut self) {
+///         match self {
+///             Combat::Approach(log) => log.lock().unwrap().push("exit Approach"),
+///             Combat::Attack(log) => log.lock().unwrap().push("exit Attack"),
+///         }
+///     }
+/// }
+/// 
+/// impl HierarchicalMode<Event> for Combat {
+///     fn handle_event(&mut self, event : &Event) -> Response<Combat> {
+///         match (self, event) {
+///             (Combat::Approach(log), Event::Tick) => Response::Switch(Combat::Attack(log.clone())),
+///             (Combat::Attack(_), Event::Tick) => Response::Handled,
+///             _ => Response::Unhandled, // Bubble everything else up to Ai.
+///         }
+///     }
+/// }
+/// 
+/// let log = Log::default();
+/// let mut automaton = AiFamily::automaton_with_mode(Ai::Patrol(log.clone()));
+/// Automaton::enter_nested(&mut automaton);
+/// 
+/// assert!(Automaton::dispatch(&mut automaton, &Event::EnemySpotted));
+/// assert!(Automaton::dispatch(&mut automaton, &Event::Tick)); // Handled by the inner Combat::Approach Mode.
+/// assert!(Automaton::dispatch(&mut automaton, &Event::EnemyLost)); // Bubbles up from Combat::Attack to Ai::Combat.
+/// assert!(!Automaton::dispatch(&mut automaton, &Event::Tick)); // Nobody handles Tick while patrolling.
+/// 
+/// assert_eq!(*log.lock().unwrap(), vec![
+///     "enter Patrol",
+///     "exit Patrol", "enter Combat", "enter Approach",
+///     "exit Approach", "enter Attack",
+///     "exit Attack", "exit Combat", "enter Patrol",
+/// ]);
+/// ```
+/// 
+pub trait HierarchicalMode<E> : Mode + ModeLifecycle {
+    /// Called to offer an `event` to the current `Mode`, after it has already been offered to (and not handled by) any
+    /// `Mode`s nested inside of this one. Returns a `Response` indicating whether the event was handled, and whether
+    /// the current `Mode` should be swapped out as a result.
+    /// 
+    fn handle_event(&mut self, event : &E) -> Response<<Self::Family as Family>::Mode>;
+
+    /// Returns the child state machine nested inside this `Mode`, if any. The default implementation returns `None`,
+    /// meaning that the `Mode` has no children.
+    /// 
+    fn submachine(&mut self) -> Option<&mut dyn Submachine<E>> { None }
+}
+
+/// Object-safe interface to an `Automaton` nested inside of a `HierarchicalMode`. This allows a parent `Mode` to expose
+/// a child `Automaton` over some other `Family` without the parent `Family` needing to know its type.
+/// 
+/// This is implemented for all `Automaton`s whose `Base` type implements `HierarchicalMode<E>`, as well as for
+/// `OrthogonalAutomaton`s whose regions do. `Submachine` can't be implemented outside of this crate, and has no
+/// methods that can be called on an `Automaton`, so that it never shadows any function with the same name on
+/// `F::Base`. Use [`Automaton::dispatch()`](struct.Automaton.html#method.dispatch) and friends instead.
+/// 
+/// See [`HierarchicalMode`](trait.HierarchicalMode.html) for more details.
+/// 
+pub trait Submachine<E> : private::NestedAutomaton<E> { }
+
+impl<T, E> Submachine<E> for T
+    where T : private::NestedAutomaton<E> + ?Sized
+{ }
+
+pub(crate) mod private {
+    // Since this trait is public but can't be named outside of the crate, its methods can only be called from inside
+    // the crate, even on an Automaton after `use mode::*`.
+    pub trait NestedAutomaton<E> {
+        fn dispatch(&mut self, event : &E) -> bool;
+        fn enter_nested(&mut self);
+        fn exit_nested(&mut self);
+    }
+}
+
+impl<F> Automaton<F>
+    where
+        F : Family + ?Sized,
+        F::Mode : BorrowMut<F::Base>,
+{
+    /// Offers `event` to the innermost active `Mode` of a hierarchical state machine, bubbling it up through each
+    /// parent `Mode` until it is handled. Returns `true` if any `Mode` handled the event.
+    /// 
+    /// See [`HierarchicalMode`](trait.HierarchicalMode.html) for more details.
+    /// 
+    pub fn dispatch<E>(automaton : &mut Self, event : &E) -> bool
+        where F::Base : HierarchicalMode<E> + Mode<Family = F>
+    {
+        let current_mode = automaton.borrow_mode_mut();
+
+        if let Some(submachine) = current_mode.submachine() {
+            if submachine.dispatch(event) {
+                return true;
+            }
+        }
+
+        match current_mode.handle_event(event) {
+            Response::Handled => true,
+            Response::Unhandled => false,
+            Response::Switch(mut next_mode) => {
+                Self::next(automaton, |mut current_mode| {
+                    Self::exit_mode(current_mode.borrow_mut());
+                    drop(current_mode);
+                    Self::enter_mode(next_mode.borrow_mut());
+                    next_mode
+                });
+                true
+            },
+        }
+    }
+
+    /// Enters the current `Mode` and then all `Mode`s nested inside of it, from the outermost `Mode` inward. Like
+    /// [`Automaton::enter()`](struct.Automaton.html#method.enter), this should be called once after the `Automaton` is
+    /// created, in order to enter the initial `Mode`s.
+    /// 
+    /// See [`HierarchicalMode`](trait.HierarchicalMode.html) for more details.
+    /// 
+    pub fn enter_nested<E>(automaton : &mut Self)
+        where F::Base : HierarchicalMode<E> + Mode<Family = F>
+    {
+        Self::enter_mode(automaton.borrow_mode_mut());
+    }
+
+    /// Exits all `Mode`s nested inside the current `Mode`, from the innermost `Mode` outward, and then the current
+    /// `Mode` itself. Like [`Automaton::exit()`](struct.Automaton.html#method.exit), this should be called once when
+    /// the `Automaton` is no longer going to be updated.
+    /// 
+    /// See [`HierarchicalMode`](trait.HierarchicalMode.html) for more details.
+    /// 
+    pub fn exit_nested<E>(automaton : &mut Self)
+        where F::Base : HierarchicalMode<E> + Mode<Family = F>
+    {
+        Self::exit_mode(automaton.borrow_mode_mut());
+    }
+
+    fn enter_mode<E>(mode : &mut F::Base)
+        where F::Base : HierarchicalMode<E> + Mode<Family = F>
+    {
+        mode.on_enter();
+        if let Some(submachine) = mode.submachine() {
+            submachine.enter_nested();
+        }
+    }
+
+    fn exit_mode<E>(mode : &mut F::Base)
+        where F::Base : HierarchicalMode<E> + Mode<Family = F>
+    {
+        if let Some(submachine) = mode.submachine() {
+            submachine.exit_nested();
+        }
+        mode.on_exit();
+    }
+}
+
+impl<F, E> private::NestedAutomaton<E> for Automaton<F>
+    where
+        F : Family + ?Sized,
+        F::Mode : BorrowMut<F::Base>,
+        F::Base : HierarchicalMode<E> + Mode<Family = F>,
+{
+    fn dispatch(&mut self, event : &E) -> bool {
+        Automaton::dispatch(self, event)
+    }
+
+    fn enter_nested(&mut self) {
+        Automaton::enter_nested(self)
+    }
+
+    fn exit_nested(&mut self) {
+        Automaton::exit_nested(self)
+    }
+}
@@ -0,0 +1,201 @@
+// Copyright 2019 Andrew Thomas Christensen
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the
+// MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+use crate::{Automaton, Family};
+use core::{any::type_name, borrow::Borrow, fmt};
+
+/// Identifies which `Mode` an `Automaton` is in. A `ModeId` is returned from
+/// [`NamedMode::mode_id()`](trait.NamedMode.html#method.mode_id) and
+/// [`Automaton::current_mode_id()`](struct.Automaton.html#method.current_mode_id).
+/// 
+/// A `ModeId` consists of the full `type_name()` of the `Mode`, including its module path, along with the name
+/// returned from [`NamedMode::mode_name()`](trait.NamedMode.html#method.mode_name). Both are compared, so two types
+/// with the same name in different modules have different `ModeId`s, and so do two variants of the same `enum`. Only
+/// the name is shown when a `ModeId` is displayed.
+/// 
+/// A `ModeId` is the default key of a `Recorder` or `Profiler`. Since it is `Copy`, `Eq`, `Ord`, and `Hash`, it can
+/// also be used as the key of a `ChangeObserver`, by passing `NamedMode::mode_id` as the `key_fn`.
+/// 
+/// # Usage
+/// ```
+/// use mode::*;
+/// 
+/// mod a { pub struct Idle; }
+/// mod b { pub struct Idle; }
+/// 
+/// assert_eq!(ModeId::of::<a::Idle>().name(), "Idle");
+/// assert_eq!(ModeId::of::<b::Idle>().name(), "Idle");
+/// assert_ne!(ModeId::of::<a::Idle>(), ModeId::of::<b::Idle>());
+/// assert_eq!(ModeId::of::<a::Idle>().to_string(), "Idle");
+/// ```
+/// 
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ModeId {
+    type_name : &'static str,
+    name : &'static str,
+}
+
+impl ModeId {
+    /// Creates a `ModeId` for the `Mode` of type `T` with the specified `name`, e.g. the name of an `enum` variant.
+    /// 
+    pub fn new<T>(name : &'static str) -> Self
+        where T : ?Sized
+    {
+        Self { type_name: type_name::<T>(), name }
+    }
+
+    /// Creates a `ModeId` for the `Mode` of type `T`, named after `T` without its module path. This is the `ModeId`
+    /// returned from `NamedMode::mode_id()` by default.
+    /// 
+    pub fn of<T>() -> Self
+        where T : ?Sized
+    {
+        Self::new::<T>(short_type_name(type_name::<T>()))
+    }
+
+    /// Returns the name of the `Mode`, as returned from `NamedMode::mode_name()`.
+    /// 
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the full `type_name()` of the `Mode`, including its module path.
+    /// 
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+}
+
+impl fmt::Display for ModeId {
+    fn fmt(&self, formatter : &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(self.name)
+    }
+}
+
+/// Trait for a `Mode` that knows its own name, so that the current `Mode` of an `Automaton` can be identified from the
+/// outside via [`Automaton::current_mode_id()`](struct.Automaton.html#method.current_mode_id), even if `F::Base` is a
+/// `dyn Trait` that doesn't implement `Debug`.
+/// 
+/// By default, `mode_name()` returns the name of the implementing type, without its module path, so implementing
+/// `NamedMode` for a `struct` only takes an empty `impl` block. For a `Family` where `Base` is a `dyn Trait`, add
+/// `NamedMode` as a supertrait of the `Base` trait. For an `enum`, override `mode_name()` to return the name of each
+/// variant instead, or use `#[derive(NamedMode)]` if the `derive` feature is enabled.
+/// 
+/// `NamedMode` is how the other features of this crate identify `Mode`s by default, e.g. `Profiler`, `Recorder`,
+/// `Automaton::trace()`, and `Automaton::state_graph()`.
+/// 
+/// # Usage
+/// ## A `Family` where `Base` is a `dyn Trait`
+/// ```
+/// # #[cfg(feature = "alloc")] {
+/// use mode::*;
+/// 
+/// struct ActivityFamily;
+/// impl Family for ActivityFamily {
+///     type Base = dyn Activity;
+///     type Mode = Box<dyn Activity>;
+/// }
+/// 
+/// trait Activity : Mode<Family = ActivityFamily> + NamedMode {
+///     fn update(self : Box<Self>) -> Box<dyn Activity>;
+/// }
+/// 
+/// struct Working;
+/// impl Mode for Working { type Family = ActivityFamily; }
+/// impl NamedMode for Working { }
+/// impl Activity for Working {
+///     fn update(self : Box<Self>) -> Box<dyn Activity> { Box::new(Sleeping) }
+/// }
+/// 
+/// struct Sleeping;
+/// impl Mode for Sleeping { type Family = ActivityFamily; }
+/// impl NamedMode for Sleeping { }
+/// impl Activity for Sleeping {
+///     fn update(self : Box<Self>) -> Box<dyn Activity> { self }
+/// }
+/// 
+/// let automaton = ActivityFamily::automaton_with_mode(Box::new(Working));
+/// assert_eq!(Automaton::current_mode_id(&automaton), ModeId::of::<Working>());
+/// 
+/// // Other features, e.g. a Profiler, identify each Mode by its ModeId.
+/// let clock = ManualClock::new();
+/// let mut profiler = Profiler::new(automaton, &clock);
+/// Profiler::next(&mut profiler, |current_mode| current_mode.update());
+/// assert_eq!(Automaton::current_mode_id(&profiler).name(), "Sleeping");
+/// let statistics = Profiler::statistics(&profiler);
+/// assert_eq!(statistics.transition_count(&ModeId::of::<Working>(), &ModeId::of::<Sleeping>()), 1);
+/// # }
+/// ```
+/// 
+/// ## A `Family` where `Base` is an `enum`
+/// ```
+/// use mode::*;
+/// 
+/// struct ActivityFamily;
+/// impl Family for ActivityFamily {
+///     type Base = Activity;
+///     type Mode = Activity;
+/// }
+/// 
+/// enum Activity { Working { hours_worked : u32 }, Sleeping }
+/// impl Mode for Activity { type Family = ActivityFamily; }
+/// 
+/// impl NamedMode for Activity {
+///     fn mode_name(&self) -> &'static str {
+///         match self {
+///             Activity::Working { .. } => "Working",
+///             Activity::Sleeping => "Sleeping",
+///         }
+///     }
+/// }
+/// 
+/// let automaton = ActivityFamily::automaton_with_mode(Activity::Working { hours_worked: 0 });
+/// assert_eq!(Automaton::current_mode_id(&automaton), ModeId::new::<Activity>("Working"));
+/// ```
+/// 
+pub trait NamedMode {
+    /// Returns the name of this `Mode`, e.g. for display in logs and diagrams. By default, this is the name of the
+    /// implementing type, without its module path.
+    /// 
+    fn mode_name(&self) -> &'static str {
+        short_type_name(type_name::<Self>())
+    }
+
+    /// Returns the `ModeId` of this `Mode`, i.e. a `ModeId` with the full type name of the implementing type and the
+    /// name returned from `mode_name()`.
+    /// 
+    fn mode_id(&self) -> ModeId {
+        ModeId::new::<Self>(self.mode_name())
+    }
+}
+
+// Strips the module path from a type name, e.g. "crate::module::Type<crate::Other>" becomes "Type<crate::Other>".
+fn short_type_name(name : &'static str) -> &'static str {
+    let path_end = name.find('<').unwrap_or(name.len());
+    match name[..path_end].rfind("::") {
+        Some(index) => &name[index + 2..],
+        None => name,
+    }
+}
+
+impl<F> Automaton<F>
+    where
+        F : Family + ?Sized,
+        F::Mode : Borrow<F::Base>,
+        F::Base : NamedMode,
+{
+    /// Returns the `ModeId` of the current `Mode`.
+    /// 
+    /// **NOTE:** Since `Automaton` implements `Deref`, this is an associated function rather than a method, so that it
+    /// doesn't shadow any function with the same name on `F::Base`. Call it as
+    /// `Automaton::current_mode_id(&automaton)`.
+    /// 
+    /// See [`NamedMode`](trait.NamedMode.html) for more details.
+    /// 
+    pub fn current_mode_id(automaton : &Self) -> ModeId {
+        automaton.borrow_mode().mode_id()
+    }
+}
@@ -27,22 +27,118 @@
 //!    `examples/activity.rs`.
 //!  - For an example demonstrating how to pass context into and out of transition functions, please see
 //!    `examples/turing.rs`.
+//!  - For an example demonstrating how to run setup and teardown code when entering and exiting `Mode`s, please see
+//!    `examples/lifecycle.rs`.
+//!  - For an example demonstrating the `transitions!` macro and exporting a state graph as a diagram, please see
+//!    `examples/diagram.rs`. Pass `--dot`, `--mermaid`, or `--plantuml` to print the diagram instead of running it.
 //! 
 //! You can run the examples using the following Cargo commands:
 //! ```shell
 //! cargo run --example enum
 //! cargo run --example activity
 //! cargo run --example turing
+//! cargo run --example lifecycle
+//! cargo run --example diagram -- --dot
 //! ```
 //! 
 //! # Getting started
 //! A good place to start reading would be the [`Automaton`](struct.Automaton.html) documentation, followed by
 //! [`Mode`](trait.Mode.html) and then [`Family`](trait.Family.html).
 //! 
+//! # `no_std` support
+//! This crate is `#![no_std]`. The core `Automaton`, `Mode`, and `Family` types only depend on `core`, and the rest of
+//! the crate is split between two cargo features:
+//! 
+//! - `alloc` enables everything that needs heap allocation, e.g. the `Mode` implementations for `Box`, `Rc`, and `Arc`,
+//!   `ObservedAutomaton`, `StackAutomaton`, `EventQueue`, and `StateGraph`.
+//! - `std` (enabled by default) implies `alloc`, and enables everything that needs the standard library, e.g.
+//!   `SharedAutomaton` and `InstantClock`.
+//! 
+//! To use this crate in a `no_std` environment, disable the default features, e.g.
+//! ```toml
+//! [dependencies]
+//! mode = { version = "0.4", default-features = false, features = ["alloc"] }
+//! ```
+//! 
+#![no_std]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
 mod automaton;
+mod clock;
+#[cfg(feature = "alloc")]
+mod diagram;
+mod downcast;
+mod event;
 mod family;
+mod guard;
+mod hierarchy;
+mod identity;
+mod lifecycle;
 mod mode;
+mod observer;
+mod orthogonal;
+mod poison;
+#[cfg(feature = "alloc")]
+mod queue;
+#[cfg(feature = "alloc")]
+mod recording;
+#[cfg(feature = "scxml")]
+mod scxml;
+#[cfg(feature = "serde")]
+mod serialization;
+#[cfg(feature = "std")]
+mod shared;
+#[cfg(feature = "alloc")]
+mod stack;
+#[cfg(feature = "alloc")]
+mod statistics;
+#[cfg(feature = "alloc")]
+mod timer;
+#[cfg(feature = "tracing")]
+mod trace;
+mod transitions;
 
 pub use self::automaton::*;
+pub use self::clock::*;
+#[cfg(feature = "alloc")]
+pub use self::diagram::*;
+pub use self::downcast::*;
+pub use self::event::*;
 pub use self::family::*;
-pub use self::mode::*;
\ No newline at end of file
+pub use self::guard::*;
+pub use self::hierarchy::*;
+pub use self::identity::*;
+pub use self::lifecycle::*;
+pub use self::mode::*;
+pub use self::observer::*;
+pub use self::orthogonal::*;
+pub use self::poison::*;
+#[cfg(feature = "alloc")]
+pub use self::queue::*;
+#[cfg(feature = "alloc")]
+pub use self::recording::*;
+#[cfg(feature = "scxml")]
+pub use self::scxml::*;
+#[cfg(feature = "std")]
+pub use self::shared::*;
+#[cfg(feature = "alloc")]
+pub use self::stack::*;
+#[cfg(feature = "alloc")]
+pub use self::statistics::*;
+#[cfg(feature = "alloc")]
+pub use self::timer::*;
+pub use self::transitions::*;
+
+#[cfg(feature = "derive")]
+pub use mode_derive::{Family, Mode, NamedMode};
+
+// Paths used by code generated from the mode-derive crate, so that it works in no_std crates.
+#[doc(hidden)]
+pub mod __private {
+    #[cfg(feature = "alloc")]
+    pub use alloc::boxed::Box;
+}
@@ -0,0 +1,133 @@
+// Copyright 2019 Andrew Thomas Christensen
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the
+// MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+use crate::{Automaton, Family};
+use core::borrow::BorrowMut;
+
+/// Optional `trait` that allows the `Base` type of a `Family` to run setup and teardown logic whenever a `Mode` becomes
+/// active in an `Automaton` or is swapped out of it.
+/// 
+/// Both functions have empty default implementations, so only the hooks that are actually needed must be implemented.
+/// The hooks are **only** run by the lifecycle-aware functions on `Automaton`, i.e.
+/// [`Automaton::next_with_lifecycle()`](struct.Automaton.html#method.next_with_lifecycle),
+/// [`Automaton::enter()`](struct.Automaton.html#method.enter), and
+/// [`Automaton::exit()`](struct.Automaton.html#method.exit). The plain `Automaton::next()` family of functions never
+/// calls them, so existing code is unaffected.
+/// 
+/// For a `Family` where `Base` is a `dyn Trait`, add `ModeLifecycle` as a supertrait of the `Base` trait, and override
+/// the hooks on each concrete `Mode` as needed.
+/// 
+/// # Usage
+/// ```
+/// # #[cfg(feature = "alloc")] {
+/// use mode::*;
+/// 
+/// struct ActivityFamily;
+/// impl Family for ActivityFamily {
+///     type Base = dyn Activity;
+///     type Mode = Box<dyn Activity>;
+/// }
+/// 
+/// trait Activity : Mode<Family = ActivityFamily> + ModeLifecycle {
+///     fn update(&mut self) -> Option<Box<dyn Activity>>;
+/// }
+/// 
+/// struct Working { hours_worked : u32 }
+/// impl Mode for Working { type Family = ActivityFamily; }
+/// impl ModeLifecycle for Working {
+///     fn on_enter(&mut self) { println!("Clocking in."); }
+///     fn on_exit(&mut self) { println!("Clocking out after {} hours.", self.hours_worked); }
+/// }
+/// impl Activity for Working {
+///     fn update(&mut self) -> Option<Box<dyn Activity>> {
+///         self.hours_worked += 1;
+///         if self.hours_worked >= 8 { Some(Box::new(Sleeping)) } else { None }
+///     }
+/// }
+/// 
+/// struct Sleeping;
+/// impl Mode for Sleeping { type Family = ActivityFamily; }
+/// impl ModeLifecycle for Sleeping {
+///     fn on_enter(&mut self) { println!("Going to bed."); }
+/// }
+/// impl Activity for Sleeping {
+///     fn update(&mut self) -> Option<Box<dyn Activity>> { None }
+/// }
+/// 
+/// let mut automaton = ActivityFamily::automaton_with_mode(Box::new(Working { hours_worked: 0 }));
+/// 
+/// // Run on_enter() for the initial Mode.
+/// Automaton::enter(&mut automaton);
+/// 
+/// // on_exit() and on_enter() are only called when update() actually returns a new Mode.
+/// while !Automaton::next_with_lifecycle(&mut automaton, |current_mode| current_mode.update()) { }
+/// # }
+/// ```
+/// 
+pub trait ModeLifecycle {
+    /// Called on a `Mode` right after it becomes the current `Mode` of an `Automaton`.
+    /// 
+    fn on_enter(&mut self) { }
+
+    /// Called on the current `Mode` of an `Automaton` right before it is swapped out for another `Mode`.
+    /// 
+    fn on_exit(&mut self) { }
+}
+
+impl<F> Automaton<F>
+    where
+        F : Family + ?Sized,
+        F::Mode : BorrowMut<F::Base>,
+        F::Base : ModeLifecycle,
+{
+    /// Calls `on_enter()` on the current `Mode`. Since `Automaton::with_mode()` and `Automaton::new()` do not run any
+    /// lifecycle hooks, this should be called once after the `Automaton` is created in order to enter the initial
+    /// `Mode`.
+    /// 
+    /// See [`ModeLifecycle`](trait.ModeLifecycle.html) for more details.
+    /// 
+    pub fn enter(automaton : &mut Self) {
+        automaton.borrow_mode_mut().on_enter();
+    }
+
+    /// Calls `on_exit()` on the current `Mode`. This should be called once when the `Automaton` is no longer going to
+    /// be updated, e.g. before it is dropped, in order to exit the final `Mode`.
+    /// 
+    /// See [`ModeLifecycle`](trait.ModeLifecycle.html) for more details.
+    /// 
+    pub fn exit(automaton : &mut Self) {
+        automaton.borrow_mode_mut().on_exit();
+    }
+
+    /// Calls `transition_fn` with a mutable reference to the current `Mode` to determine whether it should transition
+    /// out. If `transition_fn` returns `None`, the current `Mode` remains active and no lifecycle hooks are run. If it
+    /// returns `Some` new `Mode`, `on_exit()` is called on the current `Mode`, the current `Mode` is dropped, and the
+    /// new `Mode` is swapped in as current before `on_enter()` is called on it. Returns `true` if a new `Mode` was
+    /// swapped in.
+    /// 
+    /// Unlike [`next()`](struct.Automaton.html#method.next), the current `Mode` is **borrowed** by `transition_fn`
+    /// instead of being moved into it, since it must still be around to have `on_exit()` called on it after the new
+    /// `Mode` is created. State can still be moved from the current `Mode` into the new one, e.g. via
+    /// `std::mem::take()`.
+    /// 
+    /// See [`ModeLifecycle`](trait.ModeLifecycle.html) for more details.
+    /// 
+    pub fn next_with_lifecycle<T>(automaton : &mut Self, transition_fn : T) -> bool
+        where T : FnOnce(&mut F::Base) -> Option<F::Mode>
+    {
+        Self::next_with_result(automaton, |mut current_mode| {
+            match transition_fn(current_mode.borrow_mut()) {
+                Some(mut next_mode) => {
+                    current_mode.borrow_mut().on_exit();
+                    drop(current_mode);
+                    next_mode.borrow_mut().on_enter();
+                    (next_mode, true)
+                },
+                None => (current_mode, false),
+            }
+        })
+    }
+}
@@ -5,7 +5,10 @@
 // modified, or distributed except according to those terms.
 
 use crate::Family;
-use std::{rc::Rc, sync::Arc};
+#[cfg(feature = "alloc")]
+use alloc::{boxed::Box, rc::Rc};
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
+use alloc::sync::Arc;
 
 /// Trait that defines a state within some `Family`, and can be made active in an `Automaton`.
 /// 
@@ -18,6 +21,7 @@ use std::{rc::Rc, sync::Arc};
 /// 
 /// # Usage
 /// ```
+/// # #[cfg(feature = "alloc")] {
 /// use mode::*;
 /// 
 /// struct MyFamily;
@@ -26,10 +30,10 @@ use std::{rc::Rc, sync::Arc};
 ///     type Mode = Box<dyn MyMode>;
 /// }
 /// 
-/// trait MyMode : Mode<Family = MyFamily> {
+/// // AnyMode allows the current Mode to be downcast to its concrete type, e.g. to check whether it is ModeA or ModeB.
+/// trait MyMode : Mode<Family = MyFamily> + AnyMode {
 ///     // TODO: Define some common interface for ModeA and ModeB.
-///     fn is_mode_a(&self) -> bool;
-///     fn is_mode_b(&self) -> bool;
+///     fn name(&self) -> &'static str;
 /// 
 ///     // This function will be used to delegate the responsibility for swapping to the active Mode in the Automaton.
 ///     fn swap(self : Box<Self>) -> Box<dyn MyMode>;
@@ -38,8 +42,7 @@ use std::{rc::Rc, sync::Arc};
 /// struct ModeA; // TODO: Add fields.
 /// impl Mode for ModeA { type Family = MyFamily; }
 /// impl MyMode for ModeA {
-///     fn is_mode_a(&self) -> bool { true }
-///     fn is_mode_b(&self) -> bool { false }
+///     fn name(&self) -> &'static str { "A" }
 ///     fn swap(self : Box<Self>) -> Box<dyn MyMode> {
 ///         // Transition to ModeB. ModeA can swap to ModeB because both share the same Family.
 ///         Box::new(ModeB)
@@ -49,8 +52,7 @@ use std::{rc::Rc, sync::Arc};
 /// struct ModeB; // TODO: Add fields.
 /// impl Mode for ModeB { type Family = MyFamily; }
 /// impl MyMode for ModeB {
-///     fn is_mode_a(&self) -> bool { false }
-///     fn is_mode_b(&self) -> bool { true }
+///     fn name(&self) -> &'static str { "B" }
 ///     fn swap(self : Box<Self>) -> Box<dyn MyMode> { self } // Returning self means don't transition.
 /// }
 /// 
@@ -62,8 +64,12 @@ use std::{rc::Rc, sync::Arc};
 ///     Automaton::next(&mut automaton, |current_mode| current_mode.swap());
 /// 
 ///     // MyMode functions can be called on the Automaton to dispatch them to the current Mode, via Deref coercion.
-///     assert!(!automaton.is_mode_a() && automaton.is_mode_b());
+///     assert_eq!(automaton.name(), "B");
+/// 
+///     // The current Mode can also be downcast to a concrete type, via the AnyMode supertrait.
+///     assert!(Automaton::is::<ModeB>(&automaton));
 /// }
+/// # }
 /// ```
 /// 
 /// # Tying `Mode`s together with the `Family` parameter
@@ -78,6 +84,7 @@ use std::{rc::Rc, sync::Arc};
 /// place. This is possible by setting the `Family::Mode` type to a pointer type wrapping a `Family::Base`, e.g.
 /// 
 /// ```
+/// # #[cfg(feature = "alloc")] {
 /// use mode::{Family, Mode};
 /// #
 /// # trait SomeTrait : Mode<Family = FamilyWithPointerMode> { }
@@ -87,6 +94,7 @@ use std::{rc::Rc, sync::Arc};
 ///     type Base = dyn SomeTrait;
 ///     type Mode = Box<dyn SomeTrait>; // All Modes in this Family will be stored as a Box<dyn SomeTrait> internally.
 /// }
+/// # }
 /// ```
 /// 
 /// When doing so, it's usually a good idea to delegate the responsibility for swapping in the next `Mode` to the type
@@ -98,7 +106,10 @@ use std::{rc::Rc, sync::Arc};
 /// pointer into and out of the function like this can be **much** cheaper than moving around the current `Mode` by
 /// value, particularly for `Mode`s that store large amounts of data. (See example below.)
 /// 
+/// **NOTE:** These pointer `impl`s require the `alloc` feature, which is enabled by default via the `std` feature.
+/// 
 /// ```
+/// # #[cfg(feature = "alloc")] {
 /// use mode::{Family, Mode};
 /// use std::sync::Arc;
 /// 
@@ -127,6 +138,7 @@ use std::{rc::Rc, sync::Arc};
 /// impl Mode for SomeMode {
 ///     type Family = FamilyWithArcMode;
 /// }
+/// # }
 /// ```
 /// 
 pub trait Mode {
@@ -143,6 +155,7 @@ pub trait Mode {
 
 /// Blanket `impl` that allows a `Box<T : Mode>` to be used as the `Mode` associated `type` for a `Family`.
 /// 
+#[cfg(feature = "alloc")]
 impl<T, F> Mode for Box<T>
     where
         F : Family + ?Sized,
@@ -153,6 +166,7 @@ impl<T, F> Mode for Box<T>
 
 /// Blanket `impl` that allows an `Rc<T : Mode>` to be used as the `Mode` associated `type` for a `Family`.
 /// 
+#[cfg(feature = "alloc")]
 impl<T, F> Mode for Rc<T>
     where
         F : Family + ?Sized,
@@ -163,6 +177,7 @@ impl<T, F> Mode for Rc<T>
 
 /// Blanket `impl` that allows an `Arc<T : Mode>` to be used as the `Mode` associated `type` for a `Family`.
 /// 
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))]
 impl<T, F> Mode for Arc<T>
     where
         F : Family + ?Sized,
@@ -0,0 +1,353 @@
+// Copyright 2019 Andrew Thomas Christensen
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the
+// MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+use crate::Family;
+#[cfg(feature = "alloc")]
+use crate::{Automaton, PoisonError};
+#[cfg(feature = "alloc")]
+use alloc::{boxed::Box, vec::Vec};
+#[cfg(feature = "alloc")]
+use core::{borrow::{Borrow, BorrowMut}, ops::Deref};
+
+/// Trait for an object that can be registered on an [`ObservedAutomaton`](struct.ObservedAutomaton.html) in order to
+/// react to transitions from outside of the `Mode` code, e.g. for logging, refreshing a UI, or collecting analytics.
+/// 
+/// Once registered via [`ObservedAutomaton::add_observer()`](struct.ObservedAutomaton.html#method.add_observer), an
+/// `Observer` will be notified every time `ObservedAutomaton::next()` or `ObservedAutomaton::next_with_result()` is
+/// called. Since the current `Mode` is moved
+/// into the transition function by value, the outgoing `Mode` no longer exists once the transition is complete. Hence,
+/// the outgoing `Mode` is passed to `before_transition()` right before the transition function is called, and the
+/// incoming `Mode` is passed to `after_transition()` right after it has been swapped in. If the transition function
+/// returned the same `Mode`, the "outgoing" and "incoming" `Mode`s will be one and the same.
+/// 
+/// Since registered `Observer`s are boxed, `ObservedAutomaton` requires the `alloc` feature. A plain `Automaton` has no
+/// `Observer`s, and pays nothing for them.
+/// 
+/// Both functions have empty default implementations. To detect whether the `Automaton` actually changed `Mode`s, see
+/// [`ChangeObserver`](struct.ChangeObserver.html).
+/// 
+/// # Usage
+/// ```
+/// # #[cfg(feature = "alloc")] {
+/// use mode::*;
+/// use std::cell::RefCell;
+/// 
+/// struct ActivityFamily;
+/// impl Family for ActivityFamily {
+///     type Base = dyn Activity;
+///     type Mode = Box<dyn Activity>;
+/// }
+/// 
+/// trait Activity : Mode<Family = ActivityFamily> {
+///     fn name(&self) -> &'static str;
+///     fn update(self : Box<Self>) -> Box<dyn Activity>;
+/// }
+/// 
+/// struct Working;
+/// impl Mode for Working { type Family = ActivityFamily; }
+/// impl Activity for Working {
+///     fn name(&self) -> &'static str { "Working" }
+///     fn update(self : Box<Self>) -> Box<dyn Activity> { Box::new(Sleeping) }
+/// }
+/// 
+/// struct Sleeping;
+/// impl Mode for Sleeping { type Family = ActivityFamily; }
+/// impl Activity for Sleeping {
+///     fn name(&self) -> &'static str { "Sleeping" }
+///     fn update(self : Box<Self>) -> Box<dyn Activity> { self }
+/// }
+/// 
+/// // Observers don't need to be 'static, so this one can borrow the log from the enclosing scope.
+/// struct Logger<'a> {
+///     log : &'a RefCell<Vec<String>>,
+/// }
+/// 
+/// impl<'a> Observer<ActivityFamily> for Logger<'a> {
+///     fn before_transition(&mut self, outgoing : &dyn Activity) {
+///         self.log.borrow_mut().push(format!("Leaving {}", outgoing.name()));
+///     }
+/// 
+///     fn after_transition(&mut self, incoming : &dyn Activity) {
+///         self.log.borrow_mut().push(format!("Now {}", incoming.name()));
+///     }
+/// }
+/// 
+/// let log = RefCell::new(Vec::new());
+/// let mut automaton = ObservedAutomaton::new(ActivityFamily::automaton_with_mode(Box::new(Working)));
+/// ObservedAutomaton::add_observer(&mut automaton, Logger { log: &log });
+/// 
+/// ObservedAutomaton::next(&mut automaton, |current_mode| current_mode.update());
+/// assert_eq!(*log.borrow(), vec!["Leaving Working", "Now Sleeping"]);
+/// # }
+/// ```
+/// 
+pub trait Observer<F>
+    where F : Family + ?Sized
+{
+    /// Called with a reference to the current `Mode` right before it is moved into a transition function.
+    /// 
+    fn before_transition(&mut self, _outgoing : &F::Base) { }
+
+    /// Called with a reference to the current `Mode` right after the transition function returns and the `Mode` it
+    /// returned has been swapped in.
+    /// 
+    fn after_transition(&mut self, _incoming : &F::Base) { }
+}
+
+/// Handle returned from [`ObservedAutomaton::add_observer()`](struct.ObservedAutomaton.html#method.add_observer) that
+/// can be used to unregister the `Observer` later via
+/// [`ObservedAutomaton::remove_observer()`](struct.ObservedAutomaton.html#method.remove_observer).
+/// 
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct ObserverId(usize);
+
+/// An `Observer` that identifies the outgoing and incoming `Mode` of each transition by some key, and passes both keys
+/// to a callback once the transition is complete. This allows the callback to tell whether the `Automaton` stayed in
+/// the same `Mode` or changed to a different one.
+/// 
+/// The key is computed by calling `key_fn` on the current `Mode`. For a `Family` where `Base` is an `enum`,
+/// `std::mem::discriminant()` is usually a good choice of key. For a `Family` where `Base` is a `dyn Trait`, the key
+/// can be anything that distinguishes one concrete `Mode` from another, e.g. the `ModeId` returned from
+/// [`NamedMode::mode_id()`](trait.NamedMode.html#method.mode_id).
+/// 
+/// # Usage
+/// ```
+/// # #[cfg(feature = "alloc")] {
+/// use mode::*;
+/// use std::{cell::RefCell, mem::discriminant};
+/// 
+/// struct ActivityFamily;
+/// impl Family for ActivityFamily {
+///     type Base = Activity;
+///     type Mode = Activity;
+/// }
+/// 
+/// #[derive(Debug)]
+/// enum Activity {
+///     Working { hours_worked : u32 },
+///     Sleeping,
+/// }
+/// 
+/// impl Mode for Activity { type Family = ActivityFamily; }
+/// 
+/// impl Activity {
+///     fn update(self) -> Self {
+///         match self {
+///             Activity::Working { hours_worked } if hours_worked >= 1 => Activity::Sleeping,
+///             Activity::Working { hours_worked } => Activity::Working { hours_worked: hours_worked + 1 },
+///             Activity::Sleeping => Activity::Sleeping,
+///         }
+///     }
+/// }
+/// 
+/// let changes = RefCell::new(Vec::new());
+/// let automaton = ActivityFamily::automaton_with_mode(Activity::Working { hours_worked: 0 });
+/// let mut automaton = ObservedAutomaton::new(automaton);
+/// 
+/// ObservedAutomaton::add_observer(&mut automaton, ChangeObserver::new(
+///     |mode : &Activity| discriminant(mode),
+///     |outgoing, incoming| changes.borrow_mut().push(outgoing != incoming)));
+/// 
+/// for _ in 0..3 {
+///     ObservedAutomaton::next(&mut automaton, |current_mode| current_mode.update());
+/// }
+/// 
+/// // Working { 0 } -> Working { 1 } is not a change, but Working { 1 } -> Sleeping is.
+/// drop(automaton);
+/// assert_eq!(changes.into_inner(), vec![false, true, false]);
+/// # }
+/// ```
+/// 
+pub struct ChangeObserver<K, KeyFn, CallbackFn> {
+    key_fn : KeyFn,
+    callback_fn : CallbackFn,
+    outgoing_key : Option<K>,
+}
+
+impl<K, KeyFn, CallbackFn> ChangeObserver<K, KeyFn, CallbackFn>
+    where CallbackFn : FnMut(&K, &K)
+{
+    /// Creates a new `ChangeObserver` that will compute a key for each outgoing and incoming `Mode` by calling
+    /// `key_fn`, and will pass both keys to `callback_fn` after each transition, in that order.
+    /// 
+    pub fn new(key_fn : KeyFn, callback_fn : CallbackFn) -> Self {
+        Self {
+            key_fn,
+            callback_fn,
+            outgoing_key : None,
+        }
+    }
+}
+
+impl<F, K, KeyFn, CallbackFn> Observer<F> for ChangeObserver<K, KeyFn, CallbackFn>
+    where
+        F : Family + ?Sized,
+        KeyFn : FnMut(&F::Base) -> K,
+        CallbackFn : FnMut(&K, &K),
+{
+    fn before_transition(&mut self, outgoing : &F::Base) {
+        self.outgoing_key = Some((self.key_fn)(outgoing));
+    }
+
+    fn after_transition(&mut self, incoming : &F::Base) {
+        let incoming_key = (self.key_fn)(incoming);
+        if let Some(outgoing_key) = self.outgoing_key.take() {
+            (self.callback_fn)(&outgoing_key, &incoming_key);
+        }
+    }
+}
+
+/// A wrapper around an `Automaton` that notifies a list of registered [`Observer`](trait.Observer.html)s of every
+/// transition. `Observer`s are notified in the order they were added, and may borrow data with lifetime `'a`.
+/// 
+/// Observing transitions is opt-in, so that a plain `Automaton` stays allocation-free and doesn't check for `Observer`s
+/// on every transition. An `ObservedAutomaton` dereferences to the `Automaton` it wraps, so the current `Mode` can be
+/// accessed as usual, and [`borrow_mode_mut()`](#method.borrow_mode_mut) can be used to mutate it without a
+/// transition. However, in order for `Observer`s to be notified, transitions must go through
+/// [`ObservedAutomaton::next()`](#method.next) or [`ObservedAutomaton::next_with_result()`](#method.next_with_result).
+/// This requires the `alloc` feature.
+/// 
+/// See [`Observer`](trait.Observer.html) for more details.
+/// 
+#[cfg(feature = "alloc")]
+pub struct ObservedAutomaton<'a, F>
+    where F : Family + ?Sized
+{
+    automaton : Automaton<F>,
+    observers : Vec<(ObserverId, Box<dyn Observer<F> + 'a>)>,
+    next_id : usize,
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, F> ObservedAutomaton<'a, F>
+    where F : Family + ?Sized
+{
+    /// Creates a new `ObservedAutomaton` wrapping `automaton`, with no `Observer`s registered.
+    /// 
+    pub fn new(automaton : Automaton<F>) -> Self {
+        Self { automaton, observers: Vec::new(), next_id: 0 }
+    }
+
+    /// Registers `observer` on the `ObservedAutomaton`, so that it will be notified of every transition. Returns an
+    /// `ObserverId` that can be passed to [`remove_observer()`](#method.remove_observer) to unregister it.
+    /// 
+    pub fn add_observer<O>(automaton : &mut Self, observer : O) -> ObserverId
+        where O : Observer<F> + 'a
+    {
+        let id = ObserverId(automaton.next_id);
+        automaton.next_id += 1;
+        automaton.observers.push((id, Box::new(observer)));
+        id
+    }
+
+    /// Unregisters the `Observer` that was returned from [`add_observer()`](#method.add_observer) as `id`. Returns
+    /// `true` if an `Observer` was removed, or `false` if it had already been removed.
+    /// 
+    pub fn remove_observer(automaton : &mut Self, id : ObserverId) -> bool {
+        let count = automaton.observers.len();
+        automaton.observers.retain(|(entry_id, _)| *entry_id != id);
+        automaton.observers.len() != count
+    }
+
+    /// Consumes the `ObservedAutomaton`, returning the wrapped `Automaton`. All registered `Observer`s are dropped.
+    /// 
+    pub fn into_automaton(automaton : Self) -> Automaton<F> {
+        automaton.automaton
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, F> ObservedAutomaton<'a, F>
+    where
+        F : Family + ?Sized,
+        F::Mode : Borrow<F::Base>,
+{
+    /// Calls `transition_fn` on the current `Mode`, swapping in whatever `Mode` it returns, just like
+    /// [`Automaton::next()`](struct.Automaton.html#method.next). Every registered `Observer` is notified right before
+    /// `transition_fn` is called and right after the `Mode` it returned has been swapped in.
+    /// 
+    pub fn next<T>(automaton : &mut Self, transition_fn : T)
+        where T : FnOnce(F::Mode) -> F::Mode
+    {
+        Self::next_with_result(automaton, |current_mode| (transition_fn(current_mode), ()))
+    }
+
+    /// Calls `transition_fn` on the current `Mode`, swapping in the `Mode` it returns and returning the result, just
+    /// like [`Automaton::next_with_result()`](struct.Automaton.html#method.next_with_result). Every registered
+    /// `Observer` is notified right before `transition_fn` is called and right after the `Mode` it returned has been
+    /// swapped in.
+    /// 
+    pub fn next_with_result<T, R>(automaton : &mut Self, transition_fn : T) -> R
+        where T : FnOnce(F::Mode) -> (F::Mode, R)
+    {
+        for (_, observer) in automaton.observers.iter_mut() {
+            observer.before_transition(automaton.automaton.borrow_mode());
+        }
+
+        let result = Automaton::next_with_result(&mut automaton.automaton, transition_fn);
+
+        for (_, observer) in automaton.observers.iter_mut() {
+            observer.after_transition(automaton.automaton.borrow_mode());
+        }
+
+        result
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, F> ObservedAutomaton<'a, F>
+    where
+        F : Family + ?Sized,
+        F::Mode : BorrowMut<F::Base>,
+{
+    /// Returns a mutable reference to the current `Mode` as a `&mut F::Base`, just like
+    /// [`Automaton::borrow_mode_mut()`](struct.Automaton.html#method.borrow_mode_mut).
+    /// This is not a transition, so no `Observer`s are notified.
+    /// 
+    /// ```
+    /// # #[cfg(feature = "alloc")] {
+    /// use mode::*;
+    /// 
+    /// struct CounterFamily;
+    /// impl Family for CounterFamily {
+    ///     type Base = Counter;
+    ///     type Mode = Counter;
+    /// }
+    /// 
+    /// struct Counter { count : u32 }
+    /// impl Mode for Counter { type Family = CounterFamily; }
+    /// 
+    /// let mut automaton = ObservedAutomaton::new(CounterFamily::automaton_with_mode(Counter { count: 0 }));
+    /// ObservedAutomaton::borrow_mode_mut(&mut automaton).count += 1;
+    /// assert_eq!(automaton.count, 1);
+    /// # }
+    /// ```
+    /// 
+    pub fn borrow_mode_mut(automaton : &mut Self) -> &mut F::Base {
+        automaton.automaton.borrow_mode_mut()
+    }
+
+    /// Returns a mutable reference to the current `Mode` as a `&mut F::Base`, or a `PoisonError` if the wrapped
+    /// `Automaton` is poisoned, just like
+    /// [`Automaton::try_borrow_mode_mut()`](struct.Automaton.html#method.try_borrow_mode_mut).
+    /// 
+    pub fn try_borrow_mode_mut(automaton : &mut Self) -> Result<&mut F::Base, PoisonError> {
+        automaton.automaton.try_borrow_mode_mut()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a, F> Deref for ObservedAutomaton<'a, F>
+    where F : Family + ?Sized
+{
+    type Target = Automaton<F>;
+
+    /// Returns a reference to the wrapped `Automaton`.
+    /// 
+    fn deref(&self) -> &Automaton<F> {
+        &self.automaton
+    }
+}
@@ -0,0 +1,231 @@
+// Copyright 2019 Andrew Thomas Christensen
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the
+// MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+use crate::{Automaton, Family, HierarchicalMode, Mode, Submachine, hierarchy::private::NestedAutomaton};
+use core::{
+    borrow::BorrowMut,
+    ops::{Deref, DerefMut},
+};
+
+/// Represents a composite state machine made up of several independent `Automaton`s, called **regions**, that are all
+/// active at the same time. This corresponds to the concept of orthogonal regions in statecharts, e.g. a character
+/// that is in some "movement" state and some "weapon" state simultaneously.
+/// 
+/// The `R` parameter is a tuple of `Automaton`s, e.g. `(Automaton<MovementFamily>, Automaton<WeaponFamily>)`, where
+/// each `Automaton` can be over a different `Family`. Tuples of up to 8 regions are supported. Each region can be
+/// accessed by index via `Deref` coercion, e.g. `automaton.0`, which in turn allows the current `Mode` of that region
+/// to be inspected as its `F::Base`.
+/// 
+/// All regions can be transitioned at once by passing a tuple of transition functions, one per region, into
+/// [`OrthogonalAutomaton::next()`](#method.next). If each region's `Base` implements
+/// [`HierarchicalMode<E>`](trait.HierarchicalMode.html), an event can also be broadcast to all regions at once via
+/// [`OrthogonalAutomaton::dispatch()`](#method.dispatch).
+/// 
+/// # Usage
+/// ```
+/// use mode::*;
+/// 
+/// struct MovementFamily;
+/// impl Family for MovementFamily {
+///     type Base = Movement;
+///     type Mode = Movement;
+/// }
+/// 
+/// #[derive(Debug, Eq, PartialEq)]
+/// enum Movement { Standing, Walking }
+/// impl Mode for Movement { type Family = MovementFamily; }
+/// 
+/// struct WeaponFamily;
+/// impl Family for WeaponFamily {
+///     type Base = Weapon;
+///     type Mode = Weapon;
+/// }
+/// 
+/// #[derive(Debug, Eq, PartialEq)]
+/// enum Weapon { Holstered, Drawn { ammo : u32 } }
+/// impl Mode for Weapon { type Family = WeaponFamily; }
+/// 
+/// let mut automaton = OrthogonalAutomaton::new((
+///     MovementFamily::automaton_with_mode(Movement::Standing),
+///     WeaponFamily::automaton_with_mode(Weapon::Holstered),
+/// ));
+/// 
+/// // Transition both regions in a single call.
+/// OrthogonalAutomaton::next(&mut automaton, (
+///     |_| Movement::Walking,
+///     |_| Weapon::Drawn { ammo: 6 },
+/// ));
+/// 
+/// // Each region can be inspected separately.
+/// assert_eq!(*automaton.0, Movement::Walking);
+/// assert_eq!(*automaton.1, Weapon::Drawn { ammo: 6 });
+/// ```
+/// 
+pub struct OrthogonalAutomaton<R> {
+    regions : R,
+}
+
+impl<R> OrthogonalAutomaton<R> {
+    /// Creates a new `OrthogonalAutomaton` from a tuple of `Automaton`s, each of which will become a separate region.
+    /// 
+    pub fn new(regions : R) -> Self {
+        Self { regions }
+    }
+
+    /// Consumes the `OrthogonalAutomaton`, returning the tuple of `Automaton`s making up its regions.
+    /// 
+    pub fn into_regions(automaton : Self) -> R {
+        automaton.regions
+    }
+
+    /// Calls each function in `transition_fns` on the current `Mode` of the corresponding region, in order, swapping
+    /// in whatever `Mode` each one returns. This is equivalent to calling `Automaton::next()` on each region.
+    /// 
+    pub fn next<T>(automaton : &mut Self, transition_fns : T)
+        where R : Regions<T>
+    {
+        automaton.regions.next_all(transition_fns)
+    }
+
+    /// Calls each function in `transition_fns` on the current `Mode` of the corresponding region, in order, swapping
+    /// in the `Mode` each one returns and collecting the results into a tuple. This is equivalent to calling
+    /// `Automaton::next_with_result()` on each region.
+    /// 
+    pub fn next_with_result<T>(automaton : &mut Self, transition_fns : T) -> R::Results
+        where R : RegionsWithResult<T>
+    {
+        automaton.regions.next_all_with_result(transition_fns)
+    }
+
+    /// Offers `event` to **every** region, in order, as if by calling `Automaton::dispatch()` on each one. Returns
+    /// `true` if the event was handled by at least one region.
+    /// 
+    /// See [`HierarchicalMode`](trait.HierarchicalMode.html) for more details.
+    /// 
+    pub fn dispatch<E>(automaton : &mut Self, event : &E) -> bool
+        where R : Submachine<E>
+    {
+        automaton.regions.dispatch(event)
+    }
+}
+
+impl<R> Deref for OrthogonalAutomaton<R> {
+    type Target = R;
+
+    /// Returns a reference to the tuple of regions, allowing each region to be accessed by index.
+    /// 
+    fn deref(&self) -> &R {
+        &self.regions
+    }
+}
+
+impl<R> DerefMut for OrthogonalAutomaton<R> {
+    /// Returns a mutable reference to the tuple of regions, allowing each region to be accessed by index.
+    /// 
+    fn deref_mut(&mut self) -> &mut R {
+        &mut self.regions
+    }
+}
+
+/// An `OrthogonalAutomaton` can itself be nested inside a `HierarchicalMode`, in which case events are broadcast to all
+/// of its regions, and all regions are entered (in order) or exited (in reverse order) together.
+/// 
+impl<R, E> NestedAutomaton<E> for OrthogonalAutomaton<R>
+    where R : Submachine<E>
+{
+    fn dispatch(&mut self, event : &E) -> bool {
+        self.regions.dispatch(event)
+    }
+
+    fn enter_nested(&mut self) {
+        self.regions.enter_nested()
+    }
+
+    fn exit_nested(&mut self) {
+        self.regions.exit_nested()
+    }
+}
+
+/// Trait implemented for tuples of `Automaton`s that allows all of them to be transitioned at once by a tuple of
+/// transition functions `T`. See [`OrthogonalAutomaton::next()`](struct.OrthogonalAutomaton.html#method.next).
+/// 
+pub trait Regions<T> {
+    /// Calls `Automaton::next()` on each region with the corresponding function in `transition_fns`.
+    /// 
+    fn next_all(&mut self, transition_fns : T);
+}
+
+/// Trait implemented for tuples of `Automaton`s that allows all of them to be transitioned at once by a tuple of
+/// transition functions `T` that return results. See
+/// [`OrthogonalAutomaton::next_with_result()`](struct.OrthogonalAutomaton.html#method.next_with_result).
+/// 
+pub trait RegionsWithResult<T> {
+    /// The tuple of results returned from the transition functions.
+    /// 
+    type Results;
+
+    /// Calls `Automaton::next_with_result()` on each region with the corresponding function in `transition_fns`.
+    /// 
+    fn next_all_with_result(&mut self, transition_fns : T) -> Self::Results;
+}
+
+macro_rules! impl_regions {
+    ($($F:ident $T:ident $R:ident $index:tt),+) => {
+        impl<$($F, $T),+> Regions<($($T,)+)> for ($(Automaton<$F>,)+)
+            where $($F : Family + ?Sized, $T : FnOnce($F::Mode) -> $F::Mode),+
+        {
+            fn next_all(&mut self, transition_fns : ($($T,)+)) {
+                $(Automaton::next(&mut self.$index, transition_fns.$index);)+
+            }
+        }
+
+        impl<$($F, $T, $R),+> RegionsWithResult<($($T,)+)> for ($(Automaton<$F>,)+)
+            where $($F : Family + ?Sized, $T : FnOnce($F::Mode) -> ($F::Mode, $R)),+
+        {
+            type Results = ($($R,)+);
+
+            fn next_all_with_result(&mut self, transition_fns : ($($T,)+)) -> Self::Results {
+                ($(Automaton::next_with_result(&mut self.$index, transition_fns.$index),)+)
+            }
+        }
+
+        impl<E, $($F),+> NestedAutomaton<E> for ($(Automaton<$F>,)+)
+            where $($F : Family + ?Sized, $F::Mode : BorrowMut<$F::Base>,
+                $F::Base : HierarchicalMode<E> + Mode<Family = $F>),+
+        {
+            fn dispatch(&mut self, event : &E) -> bool {
+                let mut handled = false;
+                $(handled |= Automaton::dispatch(&mut self.$index, event);)+
+                handled
+            }
+
+            fn enter_nested(&mut self) {
+                $(Automaton::enter_nested(&mut self.$index);)+
+            }
+
+            fn exit_nested(&mut self) {
+                impl_regions!(@exit_reversed self, [$($index)+], []);
+            }
+        }
+    };
+
+    // Reverses the list of tuple indices, so that regions are exited in the opposite order they were entered.
+    (@exit_reversed $self:ident, [$first:tt $($rest:tt)*], [$($reversed:tt)*]) => {
+        impl_regions!(@exit_reversed $self, [$($rest)*], [$first $($reversed)*])
+    };
+    (@exit_reversed $self:ident, [], [$($reversed:tt)*]) => {
+        $(Automaton::exit_nested(&mut $self.$reversed);)*
+    };
+}
+
+impl_regions!(F0 T0 R0 0);
+impl_regions!(F0 T0 R0 0, F1 T1 R1 1);
+impl_regions!(F0 T0 R0 0, F1 T1 R1 1, F2 T2 R2 2);
+impl_regions!(F0 T0 R0 0, F1 T1 R1 1, F2 T2 R2 2, F3 T3 R3 3);
+impl_regions!(F0 T0 R0 0, F1 T1 R1 1, F2 T2 R2 2, F3 T3 R3 3, F4 T4 R4 4);
+impl_regions!(F0 T0 R0 0, F1 T1 R1 1, F2 T2 R2 2, F3 T3 R3 3, F4 T4 R4 4, F5 T5 R5 5);
+impl_regions!(F0 T0 R0 0, F1 T1 R1 1, F2 T2 R2 2, F3 T3 R3 3, F4 T4 R4 4, F5 T5 R5 5, F6 T6 R6 6);
+impl_regions!(F0 T0 R0 0, F1 T1 R1 1, F2 T2 R2 2, F3 T3 R3 3, F4 T4 R4 4, F5 T5 R5 5, F6 T6 R6 6, F7 T7 R7 7);
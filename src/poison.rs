@@ -0,0 +1,38 @@
+// Copyright 2019 Andrew Thomas Christensen
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the
+// MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+use core::fmt;
+
+/// Error returned when attempting to access the current `Mode` of an `Automaton` (or a `StackAutomaton`) that has been
+/// poisoned.
+/// 
+/// An `Automaton` becomes poisoned when a transition function passed into `Automaton::next()` (or any of its sibling
+/// functions) panics. Since the current `Mode` was moved into the transition function by value, there is no longer any
+/// `Mode` to be stored in the `Automaton` after the panic unwinds. Like a poisoned `std::sync::Mutex`, a poisoned
+/// `Automaton` will refuse to hand out references to its current `Mode` until it is repaired, in this case by calling
+/// [`Automaton::recover_with()`](struct.Automaton.html#method.recover_with) with a new `Mode`.
+/// 
+/// See [`Automaton::try_borrow_mode()`](struct.Automaton.html#method.try_borrow_mode) for more details.
+/// 
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PoisonError {
+    _private : (),
+}
+
+impl PoisonError {
+    pub(crate) fn new() -> Self {
+        Self { _private: () }
+    }
+}
+
+impl fmt::Display for PoisonError {
+    fn fmt(&self, formatter : &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "Automaton was poisoned by a panic during a previous transition")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PoisonError { }
@@ -0,0 +1,389 @@
+// Copyright 2019 Andrew Thomas Christensen
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the
+// MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+use crate::{Automaton, Family, Mode, PoisonError, Response};
+use alloc::collections::VecDeque;
+use core::{borrow::BorrowMut, fmt, ops::Deref};
+
+/// The default maximum number of internal events that an `EventQueue` will process in response to a single external
+/// event. See [`EventQueue::with_max_internal_steps()`](struct.EventQueue.html#method.with_max_internal_steps).
+/// 
+pub const DEFAULT_MAX_INTERNAL_STEPS : usize = 1000;
+
+/// Error returned from an `EventQueue` when processing a single external event causes more internal events to be
+/// processed than the configured maximum, which usually indicates that two or more `Mode`s are posting internal events
+/// to each other in an infinite loop.
+/// 
+/// When this happens, any internal events that have not been processed yet are discarded. Deferred events that were
+/// waiting to be re-delivered are not discarded, but are deferred again, and will be re-delivered after the next
+/// transition. External events that are still in the queue are kept, and will be processed the next time the
+/// `EventQueue` runs.
+/// 
+/// ```
+/// use mode::*;
+/// 
+/// struct MachineFamily;
+/// impl Family for MachineFamily {
+///     type Base = Machine;
+///     type Mode = Machine;
+/// }
+/// 
+/// #[derive(Debug, Eq, PartialEq)]
+/// enum Machine { Idle, Running }
+/// impl Mode for Machine { type Family = MachineFamily; }
+/// 
+/// enum Event { Start, Tick, Job }
+/// 
+/// impl HandlesQueuedEvent<Event> for Machine {
+///     fn handle_queued_event(&mut self, event : Event, internal : &mut InternalEvents<Event>) -> Response<Self> {
+///         match (self, event) {
+///             (Machine::Idle, Event::Start) => {
+///                 for _ in 0..3 { internal.post(Event::Tick); }
+///                 Response::Switch(Machine::Running)
+///             },
+///             (Machine::Running, _) => Response::Handled,
+///             _ => Response::Unhandled,
+///         }
+///     }
+/// 
+///     fn defers_event(&self, event : &Event) -> bool {
+///         match (self, event) {
+///             (Machine::Idle, Event::Job) => true,
+///             _ => false,
+///         }
+///     }
+/// }
+/// 
+/// let mut queue = EventQueue::with_max_internal_steps(MachineFamily::automaton_with_mode(Machine::Idle), 2);
+/// EventQueue::post(&mut queue, Event::Job);
+/// EventQueue::post(&mut queue, Event::Start);
+/// assert_eq!(EventQueue::process_next(&mut queue), Ok(true));
+/// assert_eq!(EventQueue::deferred(&queue), 1);
+/// 
+/// // The Job event is queued up behind three Ticks, so it is deferred again when the limit is hit.
+/// assert_eq!(EventQueue::process_next(&mut queue), Err(StepLimitExceeded { max_internal_steps: 2 }));
+/// assert_eq!(**queue, Machine::Running);
+/// assert_eq!(EventQueue::deferred(&queue), 1);
+/// ```
+/// 
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct StepLimitExceeded {
+    /// The maximum number of internal steps that was exceeded.
+    /// 
+    pub max_internal_steps : usize,
+}
+
+impl fmt::Display for StepLimitExceeded {
+    fn fmt(&self, formatter : &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "More than {} internal events were processed in response to a single external event",
+            self.max_internal_steps)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for StepLimitExceeded { }
+
+/// Passed into
+/// [`HandlesQueuedEvent::handle_queued_event()`](trait.HandlesQueuedEvent.html#tymethod.handle_queued_event) to allow
+/// the current `Mode` to post **internal** events to the `EventQueue`.
+/// 
+pub struct InternalEvents<E> {
+    events : VecDeque<(E, bool)>,
+}
+
+impl<E> InternalEvents<E> {
+    /// Posts an internal `event`, which will be processed after the current event, but before the next external event.
+    /// Internal events are processed in the order they were posted.
+    /// 
+    pub fn post(&mut self, event : E) {
+        self.events.push_back((event, false));
+    }
+}
+
+/// Trait for a `Mode` that can handle events of type `E` from an [`EventQueue`](struct.EventQueue.html).
+/// 
+/// This works just like [`HandlesEvent`](trait.HandlesEvent.html), except that `handle_queued_event()` is also passed
+/// an [`InternalEvents`](struct.InternalEvents.html) queue that can be used to post internal events, and the `Mode` can
+/// choose to defer certain events until after the next transition by returning `true` from
+/// [`defers_event()`](#method.defers_event).
+/// 
+pub trait HandlesQueuedEvent<E> : Mode + Sized {
+    /// Called on the current `Mode` when the `EventQueue` delivers an `event` to it. Returns a `Response` indicating
+    /// whether the event was handled, and whether the current `Mode` should be swapped out as a result. Any events
+    /// posted to `internal` will be processed before the next external event.
+    /// 
+    fn handle_queued_event(&mut self, event : E, internal : &mut InternalEvents<E>) -> Response<Self>;
+
+    /// Returns `true` if the current `Mode` wants to defer `event` until after the next transition, in which case
+    /// `handle_queued_event()` will not be called with it. The default implementation never defers any events.
+    /// 
+    fn defers_event(&self, _event : &E) -> bool { false }
+}
+
+/// A wrapper around an `Automaton` that owns a queue of events of type `E`, and delivers them to the current `Mode`
+/// with UML **run-to-completion** semantics. The `Mode` type of the `Family` must implement
+/// [`HandlesQueuedEvent<E>`](trait.HandlesQueuedEvent.html).
+/// 
+/// # Semantics
+/// **External** events are posted to the back of the queue via [`post()`](#method.post), and are processed one at a
+/// time by calling [`process_next()`](#method.process_next) or [`process_all()`](#method.process_all). Each external
+/// event is processed to completion before the next one is delivered, meaning that:
+/// 
+/// 1. If handling an event posts any **internal** events, they are delivered (in order) before the next external event.
+///    Internal events can post further internal events, up to a configurable maximum number of internal events per
+///    external event (see [`with_max_internal_steps()`](#method.with_max_internal_steps)).
+/// 2. If the current `Mode` **defers** an event (see
+///    [`HandlesQueuedEvent::defers_event()`](trait.HandlesQueuedEvent.html#method.defers_event)), the event is set
+///    aside instead of being delivered. After the next event that returns `Response::Switch`, all deferred events
+///    are re-delivered (in the order they were deferred), after any internal events posted by that transition. Each
+///    re-delivered event counts as an internal step.
+/// 
+/// # Usage
+/// ```
+/// use mode::*;
+/// 
+/// struct DoorFamily;
+/// impl Family for DoorFamily {
+///     type Base = Door;
+///     type Mode = Door;
+/// }
+/// 
+/// #[derive(Debug, Eq, PartialEq)]
+/// enum Door { Locked, Closed, Opening, Open }
+/// impl Mode for Door { type Family = DoorFamily; }
+/// 
+/// enum Event { Open, Opened, Close, Lock }
+/// 
+/// impl HandlesQueuedEvent<Event> for Door {
+///     fn handle_queued_event(&mut self, event : Event, internal : &mut InternalEvents<Event>) -> Response<Self> {
+///         match (self, event) {
+///             (Door::Closed, Event::Open) => Response::Switch(Door::Opening),
+///             (Door::Opening, Event::Opened) => Response::Switch(Door::Open),
+///             (Door::Open, Event::Close) => {
+///                 internal.post(Event::Lock); // Delivered before any other external event.
+///                 Response::Switch(Door::Closed)
+///             },
+///             (Door::Closed, Event::Lock) => Response::Switch(Door::Locked),
+///             _ => Response::Unhandled,
+///         }
+///     }
+/// 
+///     // Don't drop a Close event while the Door is still Opening. Handle it once the Door is Open instead.
+///     fn defers_event(&self, event : &Event) -> bool {
+///         match (self, event) {
+///             (Door::Opening, Event::Close) => true,
+///             _ => false,
+///         }
+///     }
+/// }
+/// 
+/// let mut queue = EventQueue::new(DoorFamily::automaton_with_mode(Door::Closed));
+/// EventQueue::post(&mut queue, Event::Open);
+/// EventQueue::post(&mut queue, Event::Close);
+/// EventQueue::post(&mut queue, Event::Opened);
+/// 
+/// assert_eq!(EventQueue::process_next(&mut queue), Ok(true));
+/// assert_eq!(**queue, Door::Opening);
+/// 
+/// // The Close event is deferred, since the Door is still Opening.
+/// assert_eq!(EventQueue::process_next(&mut queue), Ok(true));
+/// assert_eq!(**queue, Door::Opening);
+/// assert_eq!(EventQueue::deferred(&queue), 1);
+/// 
+/// // Once the Door is Open, the Close event is re-delivered, which in turn posts an internal Lock event.
+/// assert_eq!(EventQueue::process_all(&mut queue), Ok(1));
+/// assert_eq!(**queue, Door::Locked);
+/// assert_eq!(EventQueue::deferred(&queue), 0);
+/// ```
+/// 
+/// ## Catching infinite loops
+/// ```
+/// use mode::*;
+/// 
+/// struct PingFamily;
+/// impl Family for PingFamily {
+///     type Base = Ping;
+///     type Mode = Ping;
+/// }
+/// 
+/// struct Ping;
+/// impl Mode for Ping { type Family = PingFamily; }
+/// 
+/// impl HandlesQueuedEvent<()> for Ping {
+///     fn handle_queued_event(&mut self, _ : (), internal : &mut InternalEvents<()>) -> Response<Self> {
+///         internal.post(()); // Oops.
+///         Response::Handled
+///     }
+/// }
+/// 
+/// let mut queue = EventQueue::with_max_internal_steps(PingFamily::automaton_with_mode(Ping), 10);
+/// EventQueue::post(&mut queue, ());
+/// assert_eq!(EventQueue::process_all(&mut queue), Err(StepLimitExceeded { max_internal_steps: 10 }));
+/// ```
+/// 
+pub struct EventQueue<F, E>
+    where F : Family + ?Sized
+{
+    automaton : Automaton<F>,
+    external : VecDeque<E>,
+    internal : InternalEvents<E>,
+    deferred : VecDeque<E>,
+    max_internal_steps : usize,
+}
+
+impl<F, E> EventQueue<F, E>
+    where F : Family + ?Sized
+{
+    /// Creates a new `EventQueue` wrapping `automaton`, with an empty queue. At most
+    /// [`DEFAULT_MAX_INTERNAL_STEPS`](constant.DEFAULT_MAX_INTERNAL_STEPS.html) internal events will be processed per
+    /// external event.
+    /// 
+    pub fn new(automaton : Automaton<F>) -> Self {
+        Self::with_max_internal_steps(automaton, DEFAULT_MAX_INTERNAL_STEPS)
+    }
+
+    /// Creates a new `EventQueue` wrapping `automaton`, with an empty queue. At most `max_internal_steps` internal
+    /// events will be processed per external event, after which processing will fail with `StepLimitExceeded`.
+    /// 
+    pub fn with_max_internal_steps(automaton : Automaton<F>, max_internal_steps : usize) -> Self {
+        Self {
+            automaton,
+            external: VecDeque::new(),
+            internal: InternalEvents { events: VecDeque::new() },
+            deferred: VecDeque::new(),
+            max_internal_steps,
+        }
+    }
+
+    /// Posts an external `event` to the back of the queue. The event will not be delivered until
+    /// [`process_next()`](#method.process_next) or [`process_all()`](#method.process_all) is called.
+    /// 
+    pub fn post(queue : &mut Self, event : E) {
+        queue.external.push_back(event);
+    }
+
+    /// Returns the number of external events waiting in the queue.
+    /// 
+    pub fn pending(queue : &Self) -> usize {
+        queue.external.len()
+    }
+
+    /// Returns the number of events that have been deferred by the current `Mode`, and are waiting for the next
+    /// transition.
+    /// 
+    pub fn deferred(queue : &Self) -> usize {
+        queue.deferred.len()
+    }
+
+    /// Consumes the `EventQueue`, returning the wrapped `Automaton`. Any events still in the queue are dropped.
+    /// 
+    pub fn into_automaton(queue : Self) -> Automaton<F> {
+        queue.automaton
+    }
+}
+
+impl<F, E> EventQueue<F, E>
+    where
+        F : Family + ?Sized,
+        F::Mode : HandlesQueuedEvent<E>,
+{
+    /// Delivers the external event at the front of the queue, along with any internal events that result from it,
+    /// to completion. Returns `Ok(true)` if an event was processed, or `Ok(false)` if the queue was empty.
+    /// 
+    pub fn process_next(queue : &mut Self) -> Result<bool, StepLimitExceeded> {
+        let event = match queue.external.pop_front() {
+            Some(event) => event,
+            None => return Ok(false),
+        };
+
+        Self::deliver(queue, event);
+
+        let mut steps = 0;
+        while let Some((event, redelivered)) = queue.internal.events.pop_front() {
+            steps += 1;
+            if steps > queue.max_internal_steps {
+                // Put back any deferred events that were waiting to be re-delivered, ahead of any deferred since.
+                let mut deferred : VecDeque<E> = queue.internal.events.drain(..)
+                    .filter(|&(_, redelivered)| redelivered)
+                    .map(|(event, _)| event)
+                    .collect();
+                if redelivered {
+                    deferred.push_front(event);
+                }
+                deferred.extend(queue.deferred.drain(..));
+                queue.deferred = deferred;
+                return Err(StepLimitExceeded { max_internal_steps: queue.max_internal_steps });
+            }
+            Self::deliver(queue, event);
+        }
+
+        Ok(true)
+    }
+
+    /// Processes external events, one at a time, until the queue is empty. Returns the number of external events that
+    /// were processed.
+    /// 
+    pub fn process_all(queue : &mut Self) -> Result<usize, StepLimitExceeded> {
+        let mut processed = 0;
+        while Self::process_next(queue)? {
+            processed += 1;
+        }
+        Ok(processed)
+    }
+
+    fn deliver(queue : &mut Self, event : E) {
+        let current_mode = queue.automaton.mode.as_ref()
+            .expect("Cannot deliver event because the Automaton is poisoned!");
+
+        if current_mode.defers_event(&event) {
+            queue.deferred.push_back(event);
+            return;
+        }
+
+        let internal = &mut queue.internal;
+        let response = Automaton::next_with_result(&mut queue.automaton, |mut current_mode| {
+            current_mode.handle_queued_event(event, internal).swap(current_mode)
+        });
+
+        if response == Response::Switch(()) {
+            internal.events.extend(queue.deferred.drain(..).map(|event| (event, true)));
+        }
+    }
+}
+
+impl<F, E> EventQueue<F, E>
+    where
+        F : Family + ?Sized,
+        F::Mode : BorrowMut<F::Base>,
+{
+    /// Returns a mutable reference to the current `Mode` as a `&mut F::Base`, just like
+    /// [`Automaton::borrow_mode_mut()`](struct.Automaton.html#method.borrow_mode_mut).
+    /// This is not a transition, so no events are delivered.
+    /// 
+    pub fn borrow_mode_mut(queue : &mut Self) -> &mut F::Base {
+        queue.automaton.borrow_mode_mut()
+    }
+
+    /// Returns a mutable reference to the current `Mode` as a `&mut F::Base`, or a `PoisonError` if the wrapped
+    /// `Automaton` is poisoned, just like
+    /// [`Automaton::try_borrow_mode_mut()`](struct.Automaton.html#method.try_borrow_mode_mut).
+    /// 
+    pub fn try_borrow_mode_mut(queue : &mut Self) -> Result<&mut F::Base, PoisonError> {
+        queue.automaton.try_borrow_mode_mut()
+    }
+}
+
+impl<F, E> Deref for EventQueue<F, E>
+    where F : Family + ?Sized
+{
+    type Target = Automaton<F>;
+
+    /// Returns a reference to the wrapped `Automaton`.
+    /// 
+    fn deref(&self) -> &Automaton<F> {
+        &self.automaton
+    }
+}
@@ -0,0 +1,369 @@
+// Copyright 2019 Andrew Thomas Christensen
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the
+// MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+use crate::{Automaton, Family, ModeId, NamedMode, PoisonError};
+use alloc::vec::Vec;
+use core::{borrow::{Borrow, BorrowMut}, fmt, ops::Deref};
+
+/// A single entry in a [`Recording`](struct.Recording.html), describing one transition of a `Recorder`.
+/// 
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct Record<I, K> {
+    /// The position of this transition in the `Recording`, starting at `0`.
+    /// 
+    pub step : u64,
+
+    /// The input that was passed into the transition function.
+    /// 
+    pub input : I,
+
+    /// The key of the `Mode` that was current before the transition.
+    /// 
+    pub before : K,
+
+    /// The key of the `Mode` that was current after the transition.
+    /// 
+    pub after : K,
+}
+
+/// A log of every transition performed through a [`Recorder`](struct.Recorder.html), in order. A `Recording` can be
+/// replayed against a fresh `Automaton` via [`replay()`](#method.replay) in order to reproduce the exact same sequence
+/// of `Mode`s.
+/// 
+/// If the `serde` feature is enabled, a `Recording` can also be serialized, e.g. in order to attach it to a bug report,
+/// as long as its inputs and keys can be. Since `ModeId`s can't be deserialized, use
+/// [`Recorder::with_key_fn()`](struct.Recorder.html#method.with_key_fn) to record some other key in that case.
+/// 
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct Recording<I, K> {
+    records : Vec<Record<I, K>>,
+}
+
+impl<I, K> Recording<I, K> {
+    /// Creates a new, empty `Recording`.
+    /// 
+    pub fn new() -> Self {
+        Self { records: Vec::new() }
+    }
+
+    /// Returns all `Record`s in the `Recording`, in the order the transitions happened.
+    /// 
+    pub fn records(&self) -> &[Record<I, K>] {
+        &self.records
+    }
+
+    /// Returns the number of transitions in the `Recording`.
+    /// 
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    /// Returns `true` if the `Recording` doesn't contain any transitions.
+    /// 
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Feeds each recorded input into `automaton` in order, via `transition_fn`, checking that the key of the current
+    /// `Mode` (as returned by `key_fn`) matches the recorded key before and after every transition. Returns a
+    /// `Divergence` describing the first mismatch, if any.
+    /// 
+    /// The `automaton` should be in the same `Mode` that the recorded `Automaton` started in, and `transition_fn` and
+    /// `key_fn` should behave the same as the ones used to make the `Recording`.
+    /// 
+    pub fn replay_with_key_fn<F, T, KeyFn>(&self, automaton : &mut Automaton<F>, key_fn : KeyFn, mut transition_fn : T)
+        -> Result<(), Divergence<'_, K>>
+        where
+            F : Family + ?Sized,
+            F::Mode : Borrow<F::Base>,
+            T : FnMut(F::Mode, &I) -> F::Mode,
+            KeyFn : Fn(&F::Base) -> K,
+            K : PartialEq,
+    {
+        for record in &self.records {
+            let before = key_fn(automaton.borrow_mode());
+            if before != record.before {
+                return Err(Divergence { step: record.step, expected: &record.before, actual: before });
+            }
+
+            Automaton::next(automaton, |current_mode| transition_fn(current_mode, &record.input));
+
+            let after = key_fn(automaton.borrow_mode());
+            if after != record.after {
+                return Err(Divergence { step: record.step, expected: &record.after, actual: after });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<I> Recording<I, ModeId> {
+    /// Feeds each recorded input into `automaton` in order, via `transition_fn`, checking that the `ModeId` of the
+    /// current `Mode` matches the recorded `ModeId` before and after every transition. Returns a `Divergence`
+    /// describing the first mismatch, if any.
+    /// 
+    /// This works just like [`replay_with_key_fn()`](#method.replay_with_key_fn), for a `Recording` made by a
+    /// `Recorder` created via [`Recorder::new()`](struct.Recorder.html#method.new).
+    /// 
+    pub fn replay<F, T>(&self, automaton : &mut Automaton<F>, transition_fn : T) -> Result<(), Divergence<'_, ModeId>>
+        where
+            F : Family + ?Sized,
+            F::Mode : Borrow<F::Base>,
+            F::Base : NamedMode,
+            T : FnMut(F::Mode, &I) -> F::Mode,
+    {
+        self.replay_with_key_fn(automaton, <F::Base as NamedMode>::mode_id, transition_fn)
+    }
+}
+
+impl<I, K> Default for Recording<I, K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Error returned from [`Recording::replay()`](struct.Recording.html#method.replay) when the replayed `Automaton` is
+/// not in the same `Mode` as the recorded one. The expected key is borrowed from the `Recording`, so that keys don't
+/// need to be `Clone`.
+/// 
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Divergence<'a, K> {
+    /// The `step` of the `Record` at which the replay diverged from the `Recording`.
+    /// 
+    pub step : u64,
+
+    /// The key of the `Mode` that was recorded.
+    /// 
+    pub expected : &'a K,
+
+    /// The key of the `Mode` that the replayed `Automaton` was actually in.
+    /// 
+    pub actual : K,
+}
+
+impl<'a, K> fmt::Display for Divergence<'a, K>
+    where K : fmt::Debug
+{
+    fn fmt(&self, formatter : &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "Replay diverged at step {}: expected {:?}, but was {:?}", self.step, self.expected,
+            self.actual)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a, K> std::error::Error for Divergence<'a, K>
+    where K : fmt::Debug
+{ }
+
+/// A wrapper around an `Automaton` that records every transition, along with the input that caused it, into a
+/// [`Recording`](struct.Recording.html) that can later be replayed to reproduce the same sequence of `Mode`s.
+/// 
+/// Since a `Mode` is moved into the transition function by value, a `Recorder` can't keep a copy of each `Mode`.
+/// Instead, each `Mode` is identified by a **key**, which means that `Mode`s don't need to be `Clone`. By default, this
+/// is the [`ModeId`](struct.ModeId.html) of the `Mode`, so `F::Base` must implement
+/// [`NamedMode`](trait.NamedMode.html). To use some other key, create the `Recorder` via
+/// [`with_key_fn()`](#method.with_key_fn) instead. Each transition is
+/// recorded as a [`Record`](struct.Record.html) containing the input, the keys of the `Mode`s before and after the
+/// transition, and a step number.
+/// 
+/// A `Recorder` dereferences to the `Automaton` it wraps, so the current `Mode` can be accessed as usual. However, in
+/// order for all transitions to be recorded, transitions must go through
+/// [`Recorder::next()`](#method.next) or [`Recorder::next_with_result()`](#method.next_with_result), which take an
+/// input and pass a reference to it into the transition function.
+/// 
+/// # Usage
+/// ```
+/// use mode::*;
+/// 
+/// struct TurnstileFamily;
+/// impl Family for TurnstileFamily {
+///     type Base = Turnstile;
+///     type Mode = Turnstile;
+/// }
+/// 
+/// // Note that this isn't Clone.
+/// enum Turnstile { Locked, Unlocked { pushes_left : u32 } }
+/// impl Mode for Turnstile { type Family = TurnstileFamily; }
+/// 
+/// impl NamedMode for Turnstile {
+///     fn mode_name(&self) -> &'static str {
+///         match self {
+///             Turnstile::Locked => "Locked",
+///             Turnstile::Unlocked { .. } => "Unlocked",
+///         }
+///     }
+/// }
+/// 
+/// enum Input { Coin, Push }
+/// 
+/// fn step(turnstile : Turnstile, input : &Input) -> Turnstile {
+///     match (turnstile, input) {
+///         (Turnstile::Locked, Input::Coin) => Turnstile::Unlocked { pushes_left: 1 },
+///         (Turnstile::Unlocked { pushes_left: 0 }, Input::Push) => Turnstile::Locked,
+///         (Turnstile::Unlocked { pushes_left }, Input::Push) => Turnstile::Unlocked { pushes_left: pushes_left - 1 },
+///         (turnstile, _) => turnstile,
+///     }
+/// }
+/// 
+/// let mut recorder = Recorder::new(TurnstileFamily::automaton_with_mode(Turnstile::Locked));
+/// for input in vec![Input::Coin, Input::Push, Input::Push] {
+///     Recorder::next(&mut recorder, input, step);
+/// }
+/// 
+/// let (_, recording) = Recorder::into_parts(recorder);
+/// assert_eq!(recording.len(), 3);
+/// assert_eq!(recording.records()[2].step, 2);
+/// assert_eq!(recording.records()[2].after, ModeId::new::<Turnstile>("Locked"));
+/// 
+/// // Replaying the Recording against a fresh Automaton reproduces the same sequence of Modes.
+/// let mut automaton = TurnstileFamily::automaton_with_mode(Turnstile::Locked);
+/// assert_eq!(recording.replay(&mut automaton, step), Ok(()));
+/// 
+/// // If the transition logic changes, the replay reports where it diverged.
+/// let mut automaton = TurnstileFamily::automaton_with_mode(Turnstile::Locked);
+/// let divergence = recording.replay(&mut automaton, |turnstile, _| turnstile).unwrap_err();
+/// assert_eq!(divergence.step, 0);
+/// ```
+/// 
+pub struct Recorder<F, I, K, KeyFn>
+    where F : Family + ?Sized
+{
+    automaton : Automaton<F>,
+    key_fn : KeyFn,
+    recording : Recording<I, K>,
+}
+
+impl<F, I> Recorder<F, I, ModeId, fn(&F::Base) -> ModeId>
+    where
+        F : Family + ?Sized,
+        F::Mode : Borrow<F::Base>,
+        F::Base : NamedMode,
+{
+    /// Creates a new `Recorder` wrapping `automaton`, with an empty `Recording`. Each `Mode` will be identified in the
+    /// `Recording` by its `ModeId`, as returned from [`NamedMode::mode_id()`](trait.NamedMode.html#method.mode_id).
+    /// 
+    pub fn new(automaton : Automaton<F>) -> Self {
+        Self::with_key_fn(automaton, <F::Base as NamedMode>::mode_id)
+    }
+}
+
+impl<F, I, K, KeyFn> Recorder<F, I, K, KeyFn>
+    where
+        F : Family + ?Sized,
+        F::Mode : Borrow<F::Base>,
+        KeyFn : Fn(&F::Base) -> K,
+{
+    /// Creates a new `Recorder` wrapping `automaton`, with an empty `Recording`. Each `Mode` will be identified in the
+    /// `Recording` by the key returned from `key_fn`, which should then be passed into
+    /// [`Recording::replay_with_key_fn()`](struct.Recording.html#method.replay_with_key_fn) as well.
+    /// 
+    /// ```
+    /// use mode::*;
+    /// use std::mem::discriminant;
+    /// 
+    /// struct LightFamily;
+    /// impl Family for LightFamily {
+    ///     type Base = Light;
+    ///     type Mode = Light;
+    /// }
+    /// 
+    /// enum Light { Red, Green }
+    /// impl Mode for Light { type Family = LightFamily; }
+    /// 
+    /// let key_fn = |light : &Light| discriminant(light);
+    /// let mut recorder = Recorder::with_key_fn(LightFamily::automaton_with_mode(Light::Red), key_fn);
+    /// Recorder::next(&mut recorder, (), |_, _| Light::Green);
+    /// 
+    /// let (_, recording) = Recorder::into_parts(recorder);
+    /// let mut automaton = LightFamily::automaton_with_mode(Light::Red);
+    /// assert_eq!(recording.replay_with_key_fn(&mut automaton, key_fn, |_, _| Light::Green), Ok(()));
+    /// ```
+    /// 
+    pub fn with_key_fn(automaton : Automaton<F>, key_fn : KeyFn) -> Self {
+        Self { automaton, key_fn, recording: Recording::new() }
+    }
+
+    /// Calls `transition_fn` on the current `Mode` and a reference to `input`, swapping in whatever `Mode` it returns,
+    /// just like [`Automaton::next()`](struct.Automaton.html#method.next). The transition is then appended to the
+    /// `Recording`, along with `input`.
+    /// 
+    pub fn next<T>(recorder : &mut Self, input : I, transition_fn : T)
+        where T : FnOnce(F::Mode, &I) -> F::Mode
+    {
+        Self::next_with_result(recorder, input, |current_mode, input| (transition_fn(current_mode, input), ()))
+    }
+
+    /// Calls `transition_fn` on the current `Mode` and a reference to `input`, swapping in the `Mode` it returns and
+    /// returning the result, just like
+    /// [`Automaton::next_with_result()`](struct.Automaton.html#method.next_with_result). The transition is then
+    /// appended to the `Recording`, along with `input`. The result is **not** recorded.
+    /// 
+    pub fn next_with_result<T, R>(recorder : &mut Self, input : I, transition_fn : T) -> R
+        where T : FnOnce(F::Mode, &I) -> (F::Mode, R)
+    {
+        let before = (recorder.key_fn)(recorder.automaton.borrow_mode());
+        let result =
+            Automaton::next_with_result(&mut recorder.automaton, |current_mode| transition_fn(current_mode, &input));
+        let after = (recorder.key_fn)(recorder.automaton.borrow_mode());
+
+        let step = recorder.recording.records.len() as u64;
+        recorder.recording.records.push(Record { step, input, before, after });
+        result
+    }
+}
+
+impl<F, I, K, KeyFn> Recorder<F, I, K, KeyFn>
+    where F : Family + ?Sized
+{
+    /// Returns the `Recording` of all transitions so far.
+    /// 
+    pub fn recording(recorder : &Self) -> &Recording<I, K> {
+        &recorder.recording
+    }
+
+    /// Consumes the `Recorder`, returning the wrapped `Automaton` and the `Recording` of all transitions.
+    /// 
+    pub fn into_parts(recorder : Self) -> (Automaton<F>, Recording<I, K>) {
+        (recorder.automaton, recorder.recording)
+    }
+}
+
+impl<F, I, K, KeyFn> Recorder<F, I, K, KeyFn>
+    where
+        F : Family + ?Sized,
+        F::Mode : BorrowMut<F::Base>,
+{
+    /// Returns a mutable reference to the current `Mode` as a `&mut F::Base`, just like
+    /// [`Automaton::borrow_mode_mut()`](struct.Automaton.html#method.borrow_mode_mut).
+    /// This is not a transition, so nothing is recorded.
+    /// 
+    pub fn borrow_mode_mut(recorder : &mut Self) -> &mut F::Base {
+        recorder.automaton.borrow_mode_mut()
+    }
+
+    /// Returns a mutable reference to the current `Mode` as a `&mut F::Base`, or a `PoisonError` if the wrapped
+    /// `Automaton` is poisoned, just like
+    /// [`Automaton::try_borrow_mode_mut()`](struct.Automaton.html#method.try_borrow_mode_mut).
+    /// 
+    pub fn try_borrow_mode_mut(recorder : &mut Self) -> Result<&mut F::Base, PoisonError> {
+        recorder.automaton.try_borrow_mode_mut()
+    }
+}
+
+impl<F, I, K, KeyFn> Deref for Recorder<F, I, K, KeyFn>
+    where F : Family + ?Sized
+{
+    type Target = Automaton<F>;
+
+    /// Returns a reference to the wrapped `Automaton`.
+    /// 
+    fn deref(&self) -> &Automaton<F> {
+        &self.automaton
+    }
+}
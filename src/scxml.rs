@@ -0,0 +1,663 @@
+// Copyright 2019 Andrew Thomas Christensen
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the
+// MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+use crate::{Automaton, Family, Mode, StateGraph};
+use alloc::{
+    borrow::ToOwned,
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt;
+use std::error::Error;
+
+const SCXML_NAMESPACE : &str = "http://www.w3.org/2005/07/scxml";
+
+/// Error returned when an SCXML document cannot be loaded into a [`Statechart`](struct.Statechart.html).
+/// 
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ScxmlError {
+    /// The document is not well-formed XML. Contains a description of the problem.
+    /// 
+    Xml(String),
+
+    /// The root element of the document is not `<scxml>`.
+    /// 
+    NotScxml,
+
+    /// The document doesn't contain any states.
+    /// 
+    Empty,
+
+    /// A required attribute is missing from an element. Contains the names of the element and the attribute.
+    /// 
+    MissingAttribute(String, String),
+
+    /// A required child element is missing from an element. Contains the names of the element and the child element.
+    /// 
+    MissingElement(String, String),
+
+    /// More than one state has the same `id`.
+    /// 
+    DuplicateState(String),
+
+    /// A transition or `initial` attribute refers to a state that doesn't exist.
+    /// 
+    UnknownState(String),
+
+    /// The initial state of a `<state>` element, given by its `initial` attribute or `<initial>` child element, is not
+    /// one of its descendants. Contains the `id` of the `<state>`.
+    /// 
+    InvalidInitial(String),
+
+    /// The document uses an SCXML feature that isn't supported, e.g. `<parallel>` or `<history>` states, `cond`
+    /// attributes, or transitions with more than one target. Contains the name of the feature.
+    /// 
+    Unsupported(String),
+}
+
+impl fmt::Display for ScxmlError {
+    fn fmt(&self, formatter : &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScxmlError::Xml(message) => write!(formatter, "Malformed XML: {}", message),
+            ScxmlError::NotScxml => write!(formatter, "Root element is not <scxml>"),
+            ScxmlError::Empty => write!(formatter, "Document does not contain any states"),
+            ScxmlError::MissingAttribute(element, attribute) =>
+                write!(formatter, "<{}> element is missing the required \"{}\" attribute", element, attribute),
+            ScxmlError::MissingElement(element, child) =>
+                write!(formatter, "<{}> element is missing the required <{}> element", element, child),
+            ScxmlError::DuplicateState(id) => write!(formatter, "More than one state has the id \"{}\"", id),
+            ScxmlError::UnknownState(id) => write!(formatter, "No state has the id \"{}\"", id),
+            ScxmlError::InvalidInitial(id) =>
+                write!(formatter, "Initial state of \"{}\" is not one of its descendants", id),
+            ScxmlError::Unsupported(feature) => write!(formatter, "Unsupported SCXML feature: {}", feature),
+        }
+    }
+}
+
+impl Error for ScxmlError { }
+
+/// The `Family` of all state machines loaded from SCXML documents. Every `Mode` in this `Family` is an
+/// [`ScxmlMode`](struct.ScxmlMode.html), i.e. a state `id` plus some data, and transitions are performed by passing
+/// events into [`Statechart::step()`](struct.Statechart.html#method.step).
+/// 
+pub struct ScxmlFamily;
+
+impl Family for ScxmlFamily {
+    type Base = ScxmlMode;
+    type Mode = ScxmlMode;
+}
+
+/// The current state of a state machine loaded from an SCXML document, consisting of the `id` of the innermost active
+/// state, along with the values of all `<data>` elements declared in the document's `<datamodel>`.
+/// 
+/// The `<data>` values are initialized from the `expr` attribute of each `<data>` element, **without** evaluating it,
+/// and can be read and modified freely, e.g. from inside a transition function. Executable content is not supported, so
+/// the data is never modified by the `Statechart` itself.
+/// 
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ScxmlMode {
+    state : String,
+    data : BTreeMap<String, String>,
+}
+
+impl Mode for ScxmlMode {
+    type Family = ScxmlFamily;
+}
+
+impl ScxmlMode {
+    /// Returns the `id` of the innermost active state. All states containing this state are also considered active.
+    /// 
+    pub fn state(&self) -> &str {
+        &self.state
+    }
+
+    /// Returns the values of all `<data>` elements, keyed by `id`.
+    /// 
+    pub fn data(&self) -> &BTreeMap<String, String> {
+        &self.data
+    }
+
+    /// Returns the values of all `<data>` elements, keyed by `id`, for modification.
+    /// 
+    pub fn data_mut(&mut self) -> &mut BTreeMap<String, String> {
+        &mut self.data
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct Transition {
+    event : String,
+    target : Option<String>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+struct State {
+    id : String,
+    parent : Option<usize>,
+    is_final : bool,
+    initial : Option<String>,
+    transitions : Vec<Transition>,
+}
+
+/// A state machine loaded from a W3C SCXML document, which can be run via an `Automaton<ScxmlFamily>`.
+/// 
+/// The following subset of SCXML is supported:
+/// 
+/// - `<state>` elements, which may be nested inside one another to form compound states,
+/// - `<final>` elements,
+/// - `<transition>` elements with an `event` and an optional `target` (targetless transitions consume the event
+///   without changing state),
+/// - the `initial` attribute on `<scxml>` and `<state>` elements, as well as `<initial>` child elements, each of which
+///   must name a descendant of the `<state>` it belongs to, and
+/// - `<data>` elements inside a top-level `<datamodel>`.
+/// 
+/// Loading a document containing `<parallel>` or `<history>` states, eventless transitions, transitions with a `cond`
+/// attribute, or transitions with multiple targets will fail with `ScxmlError::Unsupported`. Executable content, such
+/// as `<onentry>` and `<assign>`, is skipped when loading the document, and is never run.
+/// 
+/// # Semantics
+/// When an event is passed into [`step()`](#method.step), the transitions of the innermost active state are checked in
+/// document order, followed by those of each state containing it, from the inside out. The first transition whose
+/// `event` attribute matches the event is taken. Event names are matched as in SCXML, i.e. the descriptor `"error"`
+/// matches the events `"error"` and `"error.send"`, and `"*"` matches any event. When a compound state is entered, its
+/// initial child state is entered as well, all the way down to an atomic state.
+/// 
+/// # Usage
+/// ```
+/// use mode::*;
+/// 
+/// let statechart = Statechart::parse(r#"
+///     <scxml xmlns="http://www.w3.org/2005/07/scxml" version="1.0" initial="idle">
+///         <datamodel>
+///             <data id="retries" expr="3"/>
+///         </datamodel>
+///         <state id="idle">
+///             <transition event="start" target="running"/>
+///         </state>
+///         <state id="running">
+///             <initial><transition target="loading"/></initial>
+///             <transition event="cancel" target="idle"/>
+///             <state id="loading">
+///                 <transition event="loaded" target="playing"/>
+///             </state>
+///             <state id="playing">
+///                 <transition event="end" target="done"/>
+///             </state>
+///         </state>
+///         <final id="done"/>
+///     </scxml>
+/// "#).unwrap();
+/// 
+/// let mut automaton = statechart.automaton();
+/// assert_eq!(automaton.state(), "idle");
+/// assert_eq!(automaton.data()["retries"], "3");
+/// 
+/// // Entering "running" enters its initial child state, "loading", as well.
+/// assert!(Automaton::next_with_result(&mut automaton, |mode| statechart.step(mode, "start")));
+/// assert_eq!(automaton.state(), "loading");
+/// assert_eq!(statechart.active_states(&automaton), vec!["loading", "running"]);
+/// 
+/// // Events that aren't handled by "loading" bubble up to "running".
+/// assert!(Automaton::next_with_result(&mut automaton, |mode| statechart.step(mode, "cancel")));
+/// assert_eq!(automaton.state(), "idle");
+/// 
+/// // Events that aren't handled at all leave the state unchanged.
+/// assert!(!Automaton::next_with_result(&mut automaton, |mode| statechart.step(mode, "end")));
+/// assert_eq!(automaton.state(), "idle");
+/// 
+/// for event in &["start", "loaded", "end"] {
+///     Automaton::next_with_result(&mut automaton, |mode| statechart.step(mode, event));
+/// }
+/// assert!(statechart.is_final(&automaton));
+/// 
+/// // The Statechart can also be exported as a diagram.
+/// assert_eq!(statechart.state_graph().to_mermaid(), "\
+/// stateDiagram-v2
+///     [*] --> idle
+///     idle
+///     state running {
+///         [*] --> loading
+///         loading
+///         playing
+///         loading --> playing : loaded
+///         playing --> done : end
+///     }
+///     done
+///     idle --> running : start
+///     running --> idle : cancel
+/// ");
+/// ```
+/// 
+/// # Round-tripping
+/// A `Statechart` can be written back out as SCXML via [`to_scxml()`](#method.to_scxml), which allows it to be loaded
+/// into other SCXML tools. Since executable content is skipped when the document is loaded, the output only contains
+/// the supported subset of SCXML. Loading the output again produces an identical `Statechart`.
+/// 
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Statechart {
+    name : Option<String>,
+    initial : Option<String>,
+    data : Vec<(String, Option<String>)>,
+    states : Vec<State>,
+}
+
+impl Statechart {
+    /// Loads a `Statechart` from the text of an SCXML document.
+    /// 
+    /// # Errors
+    /// Returns an `ScxmlError` if the document is malformed, refers to states that don't exist, or uses unsupported
+    /// SCXML features.
+    /// 
+    /// ```
+    /// use mode::*;
+    /// 
+    /// let result = Statechart::parse(r#"
+    ///     <scxml xmlns="http://www.w3.org/2005/07/scxml" version="1.0">
+    ///         <state id="a"><transition event="go" target="b"/></state>
+    ///     </scxml>
+    /// "#);
+    /// assert_eq!(result, Err(ScxmlError::UnknownState("b".into())));
+    /// 
+    /// // The initial state of each <state> must be one of its descendants.
+    /// let result = Statechart::parse(r#"
+    ///     <scxml xmlns="http://www.w3.org/2005/07/scxml" version="1.0">
+    ///         <state id="a" initial="b">
+    ///             <state id="b" initial="a"/>
+    ///         </state>
+    ///     </scxml>
+    /// "#);
+    /// assert_eq!(result, Err(ScxmlError::InvalidInitial("b".into())));
+    /// 
+    /// // Guarded transitions are rejected, rather than being taken unconditionally.
+    /// let result = Statechart::parse(r#"
+    ///     <scxml xmlns="http://www.w3.org/2005/07/scxml" version="1.0">
+    ///         <state id="a"><transition event="go" cond="false" target="b"/></state>
+    ///         <state id="b"/>
+    ///     </scxml>
+    /// "#);
+    /// assert_eq!(result, Err(ScxmlError::Unsupported("cond".into())));
+    /// ```
+    /// 
+    pub fn parse(text : &str) -> Result<Self, ScxmlError> {
+        let document = roxmltree::Document::parse(text).map_err(|error| ScxmlError::Xml(error.to_string()))?;
+        let root = document.root_element();
+        if !is_scxml_element(root, "scxml") {
+            return Err(ScxmlError::NotScxml);
+        }
+
+        let mut statechart = Self {
+            name: root.attribute("name").map(str::to_owned),
+            initial: root.attribute("initial").map(str::to_owned),
+            data: Vec::new(),
+            states: Vec::new(),
+        };
+
+        for child in root.children().filter(|child| child.is_element()) {
+            if is_scxml_element(child, "datamodel") {
+                for data in child.children().filter(|data| is_scxml_element(*data, "data")) {
+                    let id = required_attribute(data, "id")?;
+                    statechart.data.push((id.to_owned(), data.attribute("expr").map(str::to_owned)));
+                }
+            }
+        }
+
+        statechart.parse_states(root, None)?;
+        statechart.validate()?;
+        Ok(statechart)
+    }
+
+    fn parse_states(&mut self, element : roxmltree::Node, parent : Option<usize>) -> Result<(), ScxmlError> {
+        for child in element.children().filter(|child| child.is_element()) {
+            let is_final = is_scxml_element(child, "final");
+            if !is_final && !is_scxml_element(child, "state") {
+                for unsupported in &["parallel", "history"] {
+                    if is_scxml_element(child, unsupported) {
+                        return Err(ScxmlError::Unsupported(format!("<{}> states", unsupported)));
+                    }
+                }
+                continue;
+            }
+
+            let id = required_attribute(child, "id")?.to_owned();
+            if self.states.iter().any(|state| state.id == id) {
+                return Err(ScxmlError::DuplicateState(id));
+            }
+
+            let mut state = State {
+                id,
+                parent,
+                is_final,
+                initial: child.attribute("initial").map(str::to_owned),
+                transitions: Vec::new(),
+            };
+
+            for grandchild in child.children().filter(|grandchild| grandchild.is_element()) {
+                if is_scxml_element(grandchild, "transition") {
+                    if grandchild.has_attribute("cond") {
+                        return Err(ScxmlError::Unsupported("cond".into()));
+                    }
+                    let event = grandchild.attribute("event")
+                        .ok_or_else(|| ScxmlError::Unsupported("eventless transitions".into()))?;
+                    state.transitions.push(Transition { event: event.to_owned(), target: parse_target(grandchild)? });
+                }
+                else if is_scxml_element(grandchild, "initial") {
+                    let transition = grandchild.children().find(|node| is_scxml_element(*node, "transition"));
+                    state.initial = match transition {
+                        Some(transition) => parse_target(transition)?,
+                        None => return Err(ScxmlError::MissingElement("initial".into(), "transition".into())),
+                    };
+                }
+            }
+
+            self.states.push(state);
+            let index = self.states.len() - 1;
+            self.parse_states(child, Some(index))?;
+        }
+
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<(), ScxmlError> {
+        let targets = self.states.iter()
+            .flat_map(|state| state.transitions.iter().filter_map(|transition| transition.target.as_ref()));
+        let initials = self.states.iter().filter_map(|state| state.initial.as_ref());
+
+        for id in targets.chain(initials).chain(self.initial.iter()) {
+            self.find(id)?;
+        }
+
+        // Entering a state enters its initial state, so that must be a strict descendant, or entering would never end.
+        for (index, state) in self.states.iter().enumerate() {
+            if let Some(initial) = &state.initial {
+                let mut ancestor = self.states[self.find(initial)?].parent;
+                while ancestor.is_some() && ancestor != Some(index) {
+                    ancestor = ancestor.and_then(|ancestor| self.states[ancestor].parent);
+                }
+                if ancestor.is_none() {
+                    return Err(ScxmlError::InvalidInitial(state.id.clone()));
+                }
+            }
+        }
+
+        if self.states.is_empty() {
+            return Err(ScxmlError::Empty);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the `name` attribute of the `<scxml>` element, if any.
+    /// 
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Returns the `Mode` that the state machine starts in, i.e. the innermost initial state of the document, with all
+    /// `<data>` values set to their initial `expr`.
+    /// 
+    pub fn initial_mode(&self) -> ScxmlMode {
+        let first = match &self.initial {
+            Some(initial) => self.find(initial).expect("initial state was validated when loading the document"),
+            None => self.children(None).next().expect("document with no states was rejected when loading"),
+        };
+
+        ScxmlMode {
+            state: self.states[self.enter(first)].id.clone(),
+            data: self.data.iter().map(|(id, expr)| (id.clone(), expr.clone().unwrap_or_default())).collect(),
+        }
+    }
+
+    /// Creates a new `Automaton` that starts in the [`initial_mode()`](#method.initial_mode) of the `Statechart`.
+    /// 
+    pub fn automaton(&self) -> Automaton<ScxmlFamily> {
+        Automaton::with_mode(self.initial_mode())
+    }
+
+    /// Passes `event` into the state machine, returning the resulting `Mode` along with `true` if any transition
+    /// handled the event, or the `mode` unchanged along with `false` if not. This can be passed directly into
+    /// [`Automaton::next_with_result()`](struct.Automaton.html#method.next_with_result).
+    /// 
+    /// See the [`Statechart`](struct.Statechart.html) documentation for more details.
+    /// 
+    pub fn step(&self, mut mode : ScxmlMode, event : &str) -> (ScxmlMode, bool) {
+        let mut current = self.find(&mode.state).ok();
+
+        while let Some(index) = current {
+            let state = &self.states[index];
+            let transition = state.transitions.iter()
+                .find(|transition| matches_event(&transition.event, event));
+
+            if let Some(transition) = transition {
+                if let Some(target) = &transition.target {
+                    let target = self.find(target).expect("transition targets were validated when loading");
+                    mode.state = self.states[self.enter(target)].id.clone();
+                }
+                return (mode, true);
+            }
+
+            current = state.parent;
+        }
+
+        (mode, false)
+    }
+
+    /// Returns `true` if the current state of `mode` is a `<final>` state.
+    /// 
+    pub fn is_final(&self, mode : &ScxmlMode) -> bool {
+        self.find(&mode.state).is_ok_and(|index| self.states[index].is_final)
+    }
+
+    /// Returns the `id`s of all active states, starting with the innermost state and ending with the outermost.
+    /// 
+    pub fn active_states(&self, mode : &ScxmlMode) -> Vec<&str> {
+        let mut active = Vec::new();
+        let mut current = self.find(&mode.state).ok();
+        while let Some(index) = current {
+            active.push(self.states[index].id.as_str());
+            current = self.states[index].parent;
+        }
+        active
+    }
+
+    /// Returns a [`StateGraph`](struct.StateGraph.html) describing the `Statechart`, with compound states represented
+    /// as composite states. Each edge is labeled with the `event` of the corresponding transition. Targetless
+    /// transitions are not included.
+    /// 
+    pub fn state_graph(&self) -> StateGraph {
+        let graph = match &self.name {
+            Some(name) => StateGraph::with_name(name.as_str()),
+            None => StateGraph::new(),
+        };
+        self.subgraph(None, graph)
+    }
+
+    fn subgraph(&self, parent : Option<usize>, mut graph : StateGraph) -> StateGraph {
+        for index in self.children(parent) {
+            let state = &self.states[index];
+            if self.children(Some(index)).next().is_some() {
+                graph.add_composite_state(state.id.as_str(), self.subgraph(Some(index), StateGraph::new()));
+            }
+            else {
+                graph.add_state(state.id.as_str());
+            }
+        }
+
+        for index in self.children(parent) {
+            let state = &self.states[index];
+            for transition in &state.transitions {
+                if let Some(target) = &transition.target {
+                    graph.add_edge(state.id.as_str(), target.as_str(), Some(transition.event.as_str()));
+                }
+            }
+        }
+
+        let initial = match parent {
+            Some(parent) => self.states[parent].initial.as_deref(),
+            None => self.initial.as_deref(),
+        };
+        let first = initial.or_else(|| self.children(parent).next().map(|index| self.states[index].id.as_str()));
+        if let Some(first) = first {
+            graph.set_initial(first);
+        }
+
+        graph
+    }
+
+    /// Writes the `Statechart` out as an SCXML document.
+    /// 
+    /// # Usage
+    /// ```
+    /// use mode::*;
+    /// 
+    /// let statechart = Statechart::parse(r#"
+    ///     <scxml xmlns="http://www.w3.org/2005/07/scxml" version="1.0" name="door">
+    ///         <state id="closed">
+    ///             <onentry><log expr="'closed'"/></onentry>
+    ///             <transition event="open" target="opened"/>
+    ///         </state>
+    ///         <state id="opened">
+    ///             <transition event="close" target="closed"/>
+    ///         </state>
+    ///     </scxml>
+    /// "#).unwrap();
+    /// 
+    /// let scxml = statechart.to_scxml();
+    /// assert_eq!(scxml, r#"<scxml xmlns="http://www.w3.org/2005/07/scxml" version="1.0" name="door">
+    ///     <state id="closed">
+    ///         <transition event="open" target="opened"/>
+    ///     </state>
+    ///     <state id="opened">
+    ///         <transition event="close" target="closed"/>
+    ///     </state>
+    /// </scxml>
+    /// "#);
+    /// 
+    /// assert_eq!(Statechart::parse(&scxml).unwrap(), statechart);
+    /// ```
+    /// 
+    pub fn to_scxml(&self) -> String {
+        let mut output = format!("<scxml xmlns=\"{}\" version=\"1.0\"", SCXML_NAMESPACE);
+        if let Some(name) = &self.name {
+            output.push_str(&format!(" name=\"{}\"", escape(name)));
+        }
+        if let Some(initial) = &self.initial {
+            output.push_str(&format!(" initial=\"{}\"", escape(initial)));
+        }
+        output.push_str(">\n");
+
+        if !self.data.is_empty() {
+            output.push_str("    <datamodel>\n");
+            for (id, expr) in &self.data {
+                output.push_str(&format!("        <data id=\"{}\"", escape(id)));
+                if let Some(expr) = expr {
+                    output.push_str(&format!(" expr=\"{}\"", escape(expr)));
+                }
+                output.push_str("/>\n");
+            }
+            output.push_str("    </datamodel>\n");
+        }
+
+        for index in self.children(None) {
+            self.write_state(&mut output, index, 1);
+        }
+
+        output.push_str("</scxml>\n");
+        output
+    }
+
+    fn write_state(&self, output : &mut String, index : usize, depth : usize) {
+        let state = &self.states[index];
+        let indent = "    ".repeat(depth);
+        let element = if state.is_final { "final" } else { "state" };
+
+        output.push_str(&format!("{}<{} id=\"{}\"", indent, element, escape(&state.id)));
+        if let Some(initial) = &state.initial {
+            output.push_str(&format!(" initial=\"{}\"", escape(initial)));
+        }
+
+        let mut children = self.children(Some(index)).peekable();
+        if state.transitions.is_empty() && children.peek().is_none() {
+            output.push_str("/>\n");
+            return;
+        }
+        output.push_str(">\n");
+
+        for transition in &state.transitions {
+            output.push_str(&format!("{}    <transition event=\"{}\"", indent, escape(&transition.event)));
+            if let Some(target) = &transition.target {
+                output.push_str(&format!(" target=\"{}\"", escape(target)));
+            }
+            output.push_str("/>\n");
+        }
+
+        for child in children {
+            self.write_state(output, child, depth + 1);
+        }
+
+        output.push_str(&format!("{}</{}>\n", indent, element));
+    }
+
+    fn find(&self, id : &str) -> Result<usize, ScxmlError> {
+        self.states.iter().position(|state| state.id == id).ok_or_else(|| ScxmlError::UnknownState(id.to_owned()))
+    }
+
+    fn children(&self, parent : Option<usize>) -> impl Iterator<Item = usize> + '_ {
+        self.states.iter().enumerate().filter(move |(_, state)| state.parent == parent).map(|(index, _)| index)
+    }
+
+    // Returns the innermost state that will be active after entering the state at `index`.
+    fn enter(&self, index : usize) -> usize {
+        let next = match &self.states[index].initial {
+            Some(initial) => self.find(initial).ok(),
+            None => self.children(Some(index)).next(),
+        };
+
+        match next {
+            Some(next) => self.enter(next),
+            None => index,
+        }
+    }
+}
+
+fn is_scxml_element(node : roxmltree::Node, name : &str) -> bool {
+    node.is_element()
+        && node.tag_name().name() == name
+        && matches!(node.tag_name().namespace(), None | Some(SCXML_NAMESPACE))
+}
+
+fn required_attribute<'a>(node : roxmltree::Node<'a, '_>, attribute : &str) -> Result<&'a str, ScxmlError> {
+    node.attribute(attribute)
+        .ok_or_else(|| ScxmlError::MissingAttribute(node.tag_name().name().to_owned(), attribute.to_owned()))
+}
+
+fn parse_target(transition : roxmltree::Node) -> Result<Option<String>, ScxmlError> {
+    match transition.attribute("target") {
+        Some(target) if target.split_whitespace().count() > 1 =>
+            Err(ScxmlError::Unsupported("transitions with multiple targets".into())),
+        Some(target) => Ok(Some(target.trim().to_owned())),
+        None => Ok(None),
+    }
+}
+
+// Implements SCXML event descriptor matching, where each descriptor is a prefix of the event name made up of whole
+// dot-separated tokens, optionally followed by ".*".
+fn matches_event(descriptors : &str, event : &str) -> bool {
+    descriptors.split_whitespace().any(|descriptor| {
+        let descriptor = descriptor.trim_end_matches(".*").trim_end_matches('.');
+        descriptor == "*"
+            || event == descriptor
+            || (event.starts_with(descriptor) && event[descriptor.len()..].starts_with('.'))
+    })
+}
+
+fn escape(text : &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
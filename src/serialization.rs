@@ -0,0 +1,158 @@
+// Copyright 2019 Andrew Thomas Christensen
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the
+// MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+use crate::{Automaton, Family};
+use serde::{de::{Deserialize, Deserializer}, ser::{Error, Serialize, Serializer}};
+
+/// If the `serde` feature is enabled and `F::Mode` implements `Serialize`, `Automaton` also implements `Serialize`.
+/// Only the current `Mode` is serialized, exactly as if `F::Mode` had been serialized on its own. Attempting to
+/// serialize a poisoned `Automaton` will fail with an error.
+/// 
+/// # Usage
+/// ```
+/// use mode::*;
+/// use serde::{Deserialize, Serialize};
+/// 
+/// struct ActivityFamily;
+/// impl Family for ActivityFamily {
+///     type Base = Activity;
+///     type Mode = Activity;
+/// }
+/// 
+/// #[derive(Debug, Deserialize, Eq, PartialEq, Serialize)]
+/// enum Activity {
+///     Working { hours_worked : u32 },
+///     Sleeping { hours_rested : u32 },
+/// }
+/// 
+/// impl Mode for Activity { type Family = ActivityFamily; }
+/// 
+/// let automaton = ActivityFamily::automaton_with_mode(Activity::Working { hours_worked: 3 });
+/// let json = serde_json::to_string(&automaton).unwrap();
+/// assert_eq!(json, r#"{"Working":{"hours_worked":3}}"#);
+/// 
+/// let automaton : Automaton<ActivityFamily> = serde_json::from_str(&json).unwrap();
+/// assert_eq!(*automaton, Activity::Working { hours_worked: 3 });
+/// ```
+/// 
+/// # Families with a `dyn Trait` as `Base`
+/// Since `serde` can't serialize or deserialize trait objects directly, a `Family` where `Mode` is, e.g.,
+/// `Box<dyn Trait>` needs to tell `serde` how to map each concrete `Mode` type to and from some serializable
+/// representation. The recommended pattern is to define a **tagged** `enum` with one variant for each concrete `Mode`
+/// type in the `Family`, acting as a registry of all `Mode`s that can be saved and loaded:
+/// 
+/// 1. `impl Serialize for dyn Trait` by converting `self` into the tagged `enum` of **references**, and serializing
+///    that. This requires a `trait` function that each concrete `Mode` implements to return its own variant.
+/// 2. `impl Deserialize for Box<dyn Trait>` by deserializing the tagged `enum` of **values**, and boxing up whichever
+///    concrete `Mode` it contains.
+/// 
+/// Since `serde` already implements `Serialize` for `Box<T : Serialize + ?Sized>`, this is enough to make
+/// `Automaton<F>` serializable.
+/// 
+/// ```
+/// # #[cfg(feature = "alloc")] {
+/// use mode::*;
+/// use serde::{Deserialize, Deserializer, Serialize, Serializer};
+/// 
+/// struct ActivityFamily;
+/// impl Family for ActivityFamily {
+///     type Base = dyn Activity;
+///     type Mode = Box<dyn Activity>;
+/// }
+/// 
+/// trait Activity : Mode<Family = ActivityFamily> {
+///     fn name(&self) -> &'static str;
+/// 
+///     // Each concrete Mode returns a reference to itself, tagged with its type.
+///     fn tagged(&self) -> TaggedActivityRef<'_>;
+/// }
+/// 
+/// #[derive(Deserialize, Serialize)]
+/// struct Working { hours_worked : u32 }
+/// impl Mode for Working { type Family = ActivityFamily; }
+/// impl Activity for Working {
+///     fn name(&self) -> &'static str { "Working" }
+///     fn tagged(&self) -> TaggedActivityRef<'_> { TaggedActivityRef::Working(self) }
+/// }
+/// 
+/// #[derive(Deserialize, Serialize)]
+/// struct Sleeping { hours_rested : u32 }
+/// impl Mode for Sleeping { type Family = ActivityFamily; }
+/// impl Activity for Sleeping {
+///     fn name(&self) -> &'static str { "Sleeping" }
+///     fn tagged(&self) -> TaggedActivityRef<'_> { TaggedActivityRef::Sleeping(self) }
+/// }
+/// 
+/// // The registry of all concrete Modes in ActivityFamily. The two enums must use the same tags.
+/// #[derive(Serialize)]
+/// #[serde(tag = "type")]
+/// enum TaggedActivityRef<'a> {
+///     Working(&'a Working),
+///     Sleeping(&'a Sleeping),
+/// }
+/// 
+/// #[derive(Deserialize)]
+/// #[serde(tag = "type")]
+/// enum TaggedActivity {
+///     Working(Working),
+///     Sleeping(Sleeping),
+/// }
+/// 
+/// impl Serialize for dyn Activity {
+///     fn serialize<S : Serializer>(&self, serializer : S) -> Result<S::Ok, S::Error> {
+///         self.tagged().serialize(serializer)
+///     }
+/// }
+/// 
+/// impl<'de> Deserialize<'de> for Box<dyn Activity> {
+///     fn deserialize<D : Deserializer<'de>>(deserializer : D) -> Result<Self, D::Error> {
+///         Ok(match TaggedActivity::deserialize(deserializer)? {
+///             TaggedActivity::Working(mode) => Box::new(mode),
+///             TaggedActivity::Sleeping(mode) => Box::new(mode),
+///         })
+///     }
+/// }
+/// 
+/// let automaton = ActivityFamily::automaton_with_mode(Box::new(Sleeping { hours_rested: 5 }));
+/// let json = serde_json::to_string(&automaton).unwrap();
+/// assert_eq!(json, r#"{"type":"Sleeping","hours_rested":5}"#);
+/// 
+/// let automaton : Automaton<ActivityFamily> = serde_json::from_str(&json).unwrap();
+/// assert_eq!(automaton.name(), "Sleeping");
+/// # }
+/// ```
+/// 
+impl<F> Serialize for Automaton<F>
+    where
+        F : Family + ?Sized,
+        F::Mode : Serialize,
+{
+    fn serialize<S>(&self, serializer : S) -> Result<S::Ok, S::Error>
+        where S : Serializer
+    {
+        match self.mode.as_ref() {
+            Some(mode) => mode.serialize(serializer),
+            None => Err(S::Error::custom("Cannot serialize an Automaton that is poisoned")),
+        }
+    }
+}
+
+/// If the `serde` feature is enabled and `F::Mode` implements `Deserialize`, `Automaton` also implements
+/// `Deserialize`. The current `Mode` is deserialized exactly as if `F::Mode` were being deserialized on its own.
+/// 
+/// See the [`Serialize`](#impl-Serialize-for-Automaton%3CF%3E) implementation for more details.
+/// 
+impl<'de, F> Deserialize<'de> for Automaton<F>
+    where
+        F : Family + ?Sized,
+        F::Mode : Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer : D) -> Result<Self, D::Error>
+        where D : Deserializer<'de>
+    {
+        F::Mode::deserialize(deserializer).map(Automaton::with_mode)
+    }
+}
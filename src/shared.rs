@@ -0,0 +1,370 @@
+// Copyright 2019 Andrew Thomas Christensen
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the
+// MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+use crate::{Automaton, Family};
+use std::{
+    borrow::{Borrow, BorrowMut},
+    error::Error,
+    fmt,
+    ops::{Deref, DerefMut},
+    sync::{Arc, Condvar, Mutex, MutexGuard, TryLockError},
+    thread::{self, ThreadId},
+};
+
+/// Error returned when a `SharedAutomaton` cannot be accessed or transitioned.
+/// 
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AccessError {
+    /// A previous transition function panicked, leaving the `Automaton` without a current `Mode`. See
+    /// [`Automaton::is_poisoned()`](struct.Automaton.html#method.is_poisoned) for more details.
+    /// 
+    Poisoned,
+
+    /// The `SharedAutomaton` is currently locked or in the middle of a transition on another thread. This is only
+    /// returned from the non-blocking `try_lock()` and `try_next()` family of functions.
+    /// 
+    WouldBlock,
+
+    /// The `SharedAutomaton` was accessed from inside one of its own transition functions. Since the current `Mode` has
+    /// been moved into the transition function, there is no `Mode` to access until the transition function returns,
+    /// and waiting for it to return from the same thread would deadlock.
+    /// 
+    Reentrant,
+}
+
+impl fmt::Display for AccessError {
+    fn fmt(&self, formatter : &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AccessError::Poisoned =>
+                write!(formatter, "Automaton was poisoned by a panic during a previous transition"),
+            AccessError::WouldBlock =>
+                write!(formatter, "Automaton is locked or being transitioned by another thread"),
+            AccessError::Reentrant =>
+                write!(formatter, "Automaton was accessed from inside one of its own transition functions"),
+        }
+    }
+}
+
+impl Error for AccessError { }
+
+struct State<F>
+    where F : Family + ?Sized
+{
+    automaton : Automaton<F>,
+    transitioning_thread : Option<ThreadId>,
+}
+
+struct Inner<F>
+    where F : Family + ?Sized
+{
+    state : Mutex<State<F>>,
+    transition_finished : Condvar,
+}
+
+/// A cloneable, thread-safe handle to an `Automaton` that can be shared between threads.
+/// 
+/// All clones of a `SharedAutomaton` refer to the same underlying `Automaton`. The current `Mode` can be accessed
+/// through a [`ModeGuard`](struct.ModeGuard.html) returned from [`lock()`](#method.lock), which dereferences to
+/// `F::Base`. Transitions work just like they do on an `Automaton`, except that they only require a shared reference to
+/// the `SharedAutomaton`. A `SharedAutomaton` is `Send` and `Sync` whenever `F::Mode` is `Send`.
+/// 
+/// # Transitions and locking
+/// The internal lock is **not** held while a transition function is running, since the current `Mode` has been moved
+/// into the transition function by value. Instead, the `SharedAutomaton` is marked as being in the middle of a
+/// transition. While a transition is in progress:
+/// 
+/// - Calls to `lock()` and `next()` from **other** threads will block until the transition completes.
+/// - Calls to `try_lock()` and `try_next()` from **other** threads will return `AccessError::WouldBlock`.
+/// - **Any** access from the thread running the transition, i.e. from inside the transition function itself, will
+///   return `AccessError::Reentrant` instead of deadlocking or panicking.
+/// 
+/// If a transition function panics, the underlying `Automaton` is poisoned, and all further attempts to access it will
+/// return `AccessError::Poisoned` until [`recover_with()`](#method.recover_with) is called.
+/// 
+/// # Usage
+/// ```
+/// use mode::*;
+/// use std::thread;
+/// 
+/// struct CounterFamily;
+/// impl Family for CounterFamily {
+///     type Base = Counter;
+///     type Mode = Counter;
+/// }
+/// 
+/// #[derive(Debug, Default)]
+/// struct Counter { count : u32 }
+/// impl Mode for Counter { type Family = CounterFamily; }
+/// 
+/// let automaton = SharedAutomaton::new(CounterFamily::automaton());
+/// 
+/// let threads : Vec<_> = (0..4).map(|_| {
+///     let automaton = automaton.clone();
+///     thread::spawn(move || {
+///         for _ in 0..100 {
+///             automaton.next(|counter| Counter { count: counter.count + 1 }).unwrap();
+///         }
+///     })
+/// }).collect();
+/// 
+/// for thread in threads { thread.join().unwrap(); }
+/// assert_eq!(automaton.lock().unwrap().count, 400);
+/// 
+/// // Accessing the SharedAutomaton from inside its own transition function returns an error instead of deadlocking.
+/// let inner = automaton.clone();
+/// automaton.next(move |counter| {
+///     assert_eq!(inner.lock().err(), Some(AccessError::Reentrant));
+///     counter
+/// }).unwrap();
+/// ```
+/// 
+pub struct SharedAutomaton<F>
+    where F : Family + ?Sized
+{
+    inner : Arc<Inner<F>>,
+}
+
+impl<F> SharedAutomaton<F>
+    where F : Family + ?Sized
+{
+    /// Creates a new `SharedAutomaton` that takes ownership of the specified `automaton`.
+    /// 
+    pub fn new(automaton : Automaton<F>) -> Self {
+        Self {
+            inner : Arc::new(Inner {
+                state : Mutex::new(State { automaton, transitioning_thread: None }),
+                transition_finished : Condvar::new(),
+            }),
+        }
+    }
+
+    /// Creates a new `SharedAutomaton` with the specified `mode` as current. This is shorthand for
+    /// `SharedAutomaton::new(Automaton::with_mode(mode))`.
+    /// 
+    pub fn with_mode(mode : F::Mode) -> Self {
+        Self::new(Automaton::with_mode(mode))
+    }
+
+    /// Calls `transition_fn` on the current `Mode` to determine whether it should transition out, swapping in whatever
+    /// `Mode` it returns as a result. If another thread is in the middle of a transition, this blocks until it is
+    /// finished. See [`Automaton::next()`](struct.Automaton.html#method.next) for more details.
+    /// 
+    pub fn next<T>(&self, transition_fn : T) -> Result<(), AccessError>
+        where T : FnOnce(F::Mode) -> F::Mode
+    {
+        self.next_with_result(|mode| (transition_fn(mode), ()))
+    }
+
+    /// Calls `transition_fn` on the current `Mode` to determine whether it should transition out, swapping in the
+    /// `Mode` it returns and returning the result. If another thread is in the middle of a transition, this blocks
+    /// until it is finished. See [`Automaton::next_with_result()`](struct.Automaton.html#method.next_with_result) for
+    /// more details.
+    /// 
+    pub fn next_with_result<T, R>(&self, transition_fn : T) -> Result<R, AccessError>
+        where T : FnOnce(F::Mode) -> (F::Mode, R)
+    {
+        let state = self.wait_for_transition(self.lock_state())?;
+        self.transition(state, transition_fn)
+    }
+
+    /// Non-blocking version of [`next()`](#method.next). If the `SharedAutomaton` is locked or in the middle of a
+    /// transition on another thread, this returns `AccessError::WouldBlock` immediately without calling
+    /// `transition_fn`.
+    /// 
+    /// **NOTE:** Unlike [`Automaton::try_next()`](struct.Automaton.html#method.try_next), the "try" in this function
+    /// refers to acquiring access to the `Automaton`, not to the transition itself failing.
+    /// 
+    pub fn try_next<T>(&self, transition_fn : T) -> Result<(), AccessError>
+        where T : FnOnce(F::Mode) -> F::Mode
+    {
+        self.try_next_with_result(|mode| (transition_fn(mode), ()))
+    }
+
+    /// Non-blocking version of [`next_with_result()`](#method.next_with_result). If the `SharedAutomaton` is locked or
+    /// in the middle of a transition on another thread, this returns `AccessError::WouldBlock` immediately without
+    /// calling `transition_fn`.
+    /// 
+    pub fn try_next_with_result<T, R>(&self, transition_fn : T) -> Result<R, AccessError>
+        where T : FnOnce(F::Mode) -> (F::Mode, R)
+    {
+        let state = self.check_transition(self.try_lock_state()?)?;
+        self.transition(state, transition_fn)
+    }
+
+    /// Returns `true` if a transition function panicked and left the underlying `Automaton` without a current `Mode`.
+    /// See [`Automaton::is_poisoned()`](struct.Automaton.html#method.is_poisoned) for more details.
+    /// 
+    pub fn is_poisoned(&self) -> bool {
+        let state = self.lock_state();
+        state.transitioning_thread.is_none() && Automaton::is_poisoned(&state.automaton)
+    }
+
+    /// Repairs a poisoned `SharedAutomaton` by making `mode` the current `Mode`. If the `SharedAutomaton` is **not**
+    /// poisoned, `mode` is handed back to the caller as an `Err`. See
+    /// [`Automaton::recover_with()`](struct.Automaton.html#method.recover_with) for more details.
+    /// 
+    pub fn recover_with(&self, mode : F::Mode) -> Result<(), F::Mode> {
+        // NOTE: The lock must be held from the moment the transition finishes until the Automaton is repaired, or
+        // another thread could recover the Automaton and start a new transition in between.
+        match self.wait_for_idle(self.lock_state()) {
+            Ok(mut state) => Automaton::recover_with(&mut state.automaton, mode),
+            Err(_) => Err(mode),
+        }
+    }
+
+    fn lock_state(&self) -> MutexGuard<'_, State<F>> {
+        // NOTE: The state is never left inconsistent while the lock is held, so it's safe to ignore Mutex poisoning.
+        self.inner.state.lock().unwrap_or_else(|error| error.into_inner())
+    }
+
+    fn try_lock_state(&self) -> Result<MutexGuard<'_, State<F>>, AccessError> {
+        match self.inner.state.try_lock() {
+            Ok(state) => Ok(state),
+            Err(TryLockError::Poisoned(error)) => Ok(error.into_inner()),
+            Err(TryLockError::WouldBlock) => Err(AccessError::WouldBlock),
+        }
+    }
+
+    fn check_transition<'a>(&self, state : MutexGuard<'a, State<F>>)
+        -> Result<MutexGuard<'a, State<F>>, AccessError>
+    {
+        match state.transitioning_thread {
+            Some(thread_id) if thread_id == thread::current().id() => Err(AccessError::Reentrant),
+            Some(_) => Err(AccessError::WouldBlock),
+            None if Automaton::is_poisoned(&state.automaton) => Err(AccessError::Poisoned),
+            None => Ok(state),
+        }
+    }
+
+    fn wait_for_transition<'a>(&'a self, state : MutexGuard<'a, State<F>>)
+        -> Result<MutexGuard<'a, State<F>>, AccessError>
+    {
+        let state = self.wait_for_idle(state)?;
+        self.check_transition(state)
+    }
+
+    // Waits until no transition is in progress, without checking whether the Automaton is poisoned. The returned guard
+    // is the same lock that was held while checking, so nothing can start a transition in the meantime.
+    fn wait_for_idle<'a>(&'a self, mut state : MutexGuard<'a, State<F>>)
+        -> Result<MutexGuard<'a, State<F>>, AccessError>
+    {
+        while let Some(thread_id) = state.transitioning_thread {
+            if thread_id == thread::current().id() {
+                return Err(AccessError::Reentrant);
+            }
+
+            state = self.inner.transition_finished.wait(state).unwrap_or_else(|error| error.into_inner());
+        }
+
+        Ok(state)
+    }
+
+    fn transition<T, R>(&self, mut state : MutexGuard<'_, State<F>>, transition_fn : T) -> Result<R, AccessError>
+        where T : FnOnce(F::Mode) -> (F::Mode, R)
+    {
+        let current_mode = state.automaton.begin_transition().ok_or(AccessError::Poisoned)?;
+        state.transitioning_thread = Some(thread::current().id());
+        drop(state);
+
+        // NOTE: This marks the transition as finished when dropped, even if transition_fn panics.
+        let guard = TransitionGuard { shared: self };
+        let (next_mode, result) = transition_fn(current_mode);
+        self.lock_state().automaton.end_transition(next_mode);
+        drop(guard);
+
+        Ok(result)
+    }
+}
+
+struct TransitionGuard<'a, F>
+    where F : Family + ?Sized
+{
+    shared : &'a SharedAutomaton<F>,
+}
+
+impl<F> Drop for TransitionGuard<'_, F>
+    where F : Family + ?Sized
+{
+    fn drop(&mut self) {
+        self.shared.lock_state().transitioning_thread = None;
+        self.shared.inner.transition_finished.notify_all();
+    }
+}
+
+impl<F> SharedAutomaton<F>
+    where
+        F : Family + ?Sized,
+        F::Mode : Borrow<F::Base>,
+{
+    /// Locks the `SharedAutomaton`, returning a `ModeGuard` that dereferences to the current `Mode` as an `F::Base`.
+    /// No other thread can access the `SharedAutomaton` until the `ModeGuard` is dropped. If another thread is in the
+    /// middle of a transition, this blocks until it is finished.
+    /// 
+    pub fn lock(&self) -> Result<ModeGuard<'_, F>, AccessError> {
+        let state = self.wait_for_transition(self.lock_state())?;
+        Ok(ModeGuard { state })
+    }
+
+    /// Non-blocking version of [`lock()`](#method.lock). If the `SharedAutomaton` is locked or in the middle of a
+    /// transition on another thread, this returns `AccessError::WouldBlock` immediately.
+    /// 
+    pub fn try_lock(&self) -> Result<ModeGuard<'_, F>, AccessError> {
+        let state = self.check_transition(self.try_lock_state()?)?;
+        Ok(ModeGuard { state })
+    }
+}
+
+impl<F> Clone for SharedAutomaton<F>
+    where F : Family + ?Sized
+{
+    /// Returns a new handle to the same underlying `Automaton`.
+    /// 
+    fn clone(&self) -> Self {
+        Self { inner: self.inner.clone() }
+    }
+}
+
+impl<F> From<Automaton<F>> for SharedAutomaton<F>
+    where F : Family + ?Sized
+{
+    fn from(automaton : Automaton<F>) -> Self {
+        Self::new(automaton)
+    }
+}
+
+/// RAII guard returned from [`SharedAutomaton::lock()`](struct.SharedAutomaton.html#method.lock) that dereferences to
+/// the current `Mode` of the `SharedAutomaton` as an `F::Base`. The `SharedAutomaton` stays locked until the guard is
+/// dropped.
+/// 
+pub struct ModeGuard<'a, F>
+    where
+        F : Family + ?Sized,
+        F::Mode : Borrow<F::Base>,
+{
+    state : MutexGuard<'a, State<F>>,
+}
+
+impl<F> Deref for ModeGuard<'_, F>
+    where
+        F : Family + ?Sized,
+        F::Mode : Borrow<F::Base>,
+{
+    type Target = F::Base;
+
+    fn deref(&self) -> &F::Base {
+        self.state.automaton.borrow_mode()
+    }
+}
+
+impl<F> DerefMut for ModeGuard<'_, F>
+    where
+        F : Family + ?Sized,
+        F::Mode : Borrow<F::Base> + BorrowMut<F::Base>,
+{
+    fn deref_mut(&mut self) -> &mut F::Base {
+        self.state.automaton.borrow_mode_mut()
+    }
+}
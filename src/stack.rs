@@ -0,0 +1,437 @@
+// Copyright 2019 Andrew Thomas Christensen
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the
+// MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+use crate::{Family, PoisonError};
+use alloc::{vec, vec::Vec};
+use core::{
+    borrow::{Borrow, BorrowMut},
+    fmt,
+    ops::{Deref, DerefMut},
+};
+
+/// Represents a transition requested by the `Mode` on top of a `StackAutomaton`. This is returned from the transition
+/// function passed into [`StackAutomaton::next()`](struct.StackAutomaton.html#method.next) in order to tell the
+/// `StackAutomaton` what to do with its stack of `Mode`s.
+/// 
+/// Since the current `Mode` is moved into the transition function by value, any `Transition` that keeps the current
+/// `Mode` on the stack hands it back, just like the transition function passed into `Automaton::next()` would.
+/// 
+#[derive(Debug)]
+pub enum Transition<M> {
+    /// Puts the specified `Mode`, usually the current `Mode`, back on top of the stack.
+    /// 
+    Stay(M),
+
+    /// Replaces the `Mode` on top of the stack with the specified `Mode`. Since the current `Mode` was moved into the
+    /// transition function, any state it owns can be moved into the new `Mode`.
+    /// 
+    Switch(M),
+
+    /// Puts the first `Mode`, usually the current `Mode`, back on the stack, and then pushes the second `Mode` on top
+    /// of it, making it current. The first `Mode` will become current again once the second `Mode` is popped.
+    /// 
+    Push(M, M),
+
+    /// Pops the `Mode` on top of the stack, making the `Mode` beneath it current.
+    /// 
+    Pop,
+
+    /// Pops the `Mode` on top of the stack, and then keeps popping `Mode`s off the stack until **at most** the
+    /// specified number of `Mode`s remain. Passing `1` returns to the `Mode` at the bottom of the stack.
+    /// 
+    PopTo(usize),
+}
+
+/// Represents a pushdown state machine over a stack of `Mode`s within the same `Family`.
+/// 
+/// A `StackAutomaton` works just like an [`Automaton`](struct.Automaton.html), except that instead of a single, active
+/// `Mode`, it keeps track of a **stack** of `Mode`s, the topmost of which is considered current. This is a natural fit
+/// for state machines that are shaped like a stack, e.g. menu flows where opening a menu should return to the previous
+/// screen once the menu is closed.
+/// 
+/// Like an `Automaton`, the current `Mode` is accessible via `borrow_mode()` and `borrow_mode_mut()`, or via `Deref`
+/// coercion. Since a `StackAutomaton` uses the same `Family` and `Mode` `trait`s as an `Automaton`, any existing `Mode`
+/// can be pushed onto a `StackAutomaton` without modification.
+/// 
+/// # Transitions
+/// The `StackAutomaton::next()` function moves the current `Mode` off the top of the stack and into a transition
+/// function, which returns a [`Transition`](enum.Transition.html) that describes what should happen to the stack. Just
+/// like with `Automaton::next()`, this allows a `Transition::Switch` to move state out of the current `Mode` and into
+/// the next one. If the transition function decides to keep the current `Mode` on the stack, e.g. in order to `Push`
+/// another `Mode` on top of it, it simply hands the current `Mode` back inside the `Transition`.
+/// 
+/// If the transition function panics, the current `Mode` is lost, and the `StackAutomaton` becomes **poisoned**, just
+/// like an `Automaton`. See [`is_poisoned()`](#method.is_poisoned) for more details.
+/// 
+/// # Usage
+/// ```
+/// use mode::*;
+/// 
+/// struct MenuFamily;
+/// impl Family for MenuFamily {
+///     type Base = dyn Menu;
+///     type Mode = Box<dyn Menu>;
+/// }
+/// 
+/// enum Input { Back, Pause, Settings }
+/// 
+/// trait Menu : Mode<Family = MenuFamily> {
+///     fn name(&self) -> &'static str;
+///     fn handle(self : Box<Self>, input : Input) -> Transition<Box<dyn Menu>>;
+/// }
+/// 
+/// struct InGame;
+/// impl Mode for InGame { type Family = MenuFamily; }
+/// impl Menu for InGame {
+///     fn name(&self) -> &'static str { "InGame" }
+///     fn handle(self : Box<Self>, input : Input) -> Transition<Box<dyn Menu>> {
+///         match input {
+///             Input::Pause => Transition::Push(self, Box::new(PauseMenu { selection: 0 })),
+///             _ => Transition::Stay(self),
+///         }
+///     }
+/// }
+/// 
+/// struct PauseMenu { selection : usize }
+/// impl Mode for PauseMenu { type Family = MenuFamily; }
+/// impl Menu for PauseMenu {
+///     fn name(&self) -> &'static str { "PauseMenu" }
+///     fn handle(self : Box<Self>, input : Input) -> Transition<Box<dyn Menu>> {
+///         match input {
+///             Input::Back => Transition::Pop,
+///             // Move the selection out of the PauseMenu, so that the SettingsMenu can return to it later.
+///             Input::Settings => Transition::Switch(Box::new(SettingsMenu { return_to: self.selection })),
+///             _ => Transition::Stay(self),
+///         }
+///     }
+/// }
+/// 
+/// struct SettingsMenu { return_to : usize }
+/// impl Mode for SettingsMenu { type Family = MenuFamily; }
+/// impl Menu for SettingsMenu {
+///     fn name(&self) -> &'static str { "SettingsMenu" }
+///     fn handle(self : Box<Self>, input : Input) -> Transition<Box<dyn Menu>> {
+///         match input {
+///             Input::Back => Transition::Switch(Box::new(PauseMenu { selection: self.return_to })),
+///             Input::Pause => Transition::PopTo(1), // Return straight to the game.
+///             _ => Transition::Stay(self),
+///         }
+///     }
+/// }
+/// 
+/// let mut automaton = StackAutomaton::<MenuFamily>::with_mode(Box::new(InGame));
+/// 
+/// StackAutomaton::next(&mut automaton, |current_mode| current_mode.handle(Input::Pause));
+/// StackAutomaton::next(&mut automaton, |current_mode| current_mode.handle(Input::Settings));
+/// assert_eq!(automaton.name(), "SettingsMenu");
+/// assert_eq!(StackAutomaton::len(&automaton), 2);
+/// 
+/// StackAutomaton::next(&mut automaton, |current_mode| current_mode.handle(Input::Back));
+/// assert_eq!(automaton.name(), "PauseMenu");
+/// 
+/// StackAutomaton::next(&mut automaton, |current_mode| current_mode.handle(Input::Back));
+/// assert_eq!(automaton.name(), "InGame");
+/// assert_eq!(StackAutomaton::len(&automaton), 1);
+/// 
+/// StackAutomaton::next(&mut automaton, |current_mode| current_mode.handle(Input::Pause));
+/// StackAutomaton::next(&mut automaton, |current_mode| current_mode.handle(Input::Settings));
+/// StackAutomaton::next(&mut automaton, |current_mode| current_mode.handle(Input::Pause));
+/// assert_eq!(automaton.name(), "InGame");
+/// ```
+/// 
+/// # Empty stacks
+/// Popping the last `Mode` off the stack is allowed, and leaves the `StackAutomaton` empty. This can be used to signal
+/// that the state machine has finished, e.g. that the user backed out of the root menu. An empty `StackAutomaton` will
+/// panic if `borrow_mode()`, `borrow_mode_mut()`, or `next()` is called on it, so use
+/// [`is_empty()`](#method.is_empty) or [`try_borrow_mode()`](#method.try_borrow_mode), which returns `Ok(None)` for an
+/// empty stack, to check for this condition.
+/// 
+pub struct StackAutomaton<F>
+    where F : Family + ?Sized
+{
+    modes : Vec<F::Mode>,
+    poisoned : bool,
+}
+
+impl<F> StackAutomaton<F>
+    where F : Family + ?Sized
+{
+    /// Creates a new `StackAutomaton` with the specified `mode` as the only `Mode` on the stack.
+    /// 
+    pub fn with_mode(mode : F::Mode) -> Self {
+        Self {
+            modes : vec![mode],
+            poisoned : false,
+        }
+    }
+
+    /// Moves the `Mode` on top of the stack into `transition_fn` to determine whether it should transition, and then
+    /// updates the stack based on the `Transition` it returns.
+    /// 
+    /// See [`StackAutomaton`](struct.StackAutomaton.html) for more details.
+    /// 
+    pub fn next<T>(automaton : &mut Self, transition_fn : T)
+        where T : FnOnce(F::Mode) -> Transition<F::Mode>
+    {
+        Self::next_with_result(automaton, |current_mode| (transition_fn(current_mode), ()))
+    }
+
+    /// Moves the `Mode` on top of the stack into `transition_fn` to determine whether it should transition, and then
+    /// updates the stack based on the `Transition` it returns. Unlike [`next()`](#method.next), `transition_fn` returns
+    /// a tuple containing a `Transition` as well as a return value in the second parameter, which will be returned from
+    /// this function after the stack has been updated.
+    /// 
+    pub fn next_with_result<T, R>(automaton : &mut Self, transition_fn : T) -> R
+        where T : FnOnce(F::Mode) -> (Transition<F::Mode>, R)
+    {
+        assert!(!automaton.poisoned, "Cannot transition the current Mode because the StackAutomaton is poisoned!");
+        let current_mode =
+            automaton.modes.pop().expect("Cannot transition the current Mode because the stack is empty!");
+
+        // If transition_fn panics, this is never cleared, since the current Mode went down with it.
+        automaton.poisoned = true;
+        let (transition, result) = transition_fn(current_mode);
+        automaton.poisoned = false;
+
+        match transition {
+            Transition::Stay(mode) | Transition::Switch(mode) => automaton.modes.push(mode),
+            Transition::Push(mode, next_mode) => {
+                automaton.modes.push(mode);
+                automaton.modes.push(next_mode);
+            },
+            Transition::Pop => { },
+            Transition::PopTo(depth) => automaton.modes.truncate(depth),
+        }
+
+        result
+    }
+
+    /// Pushes `mode` onto the top of the stack, making it the current `Mode`.
+    /// 
+    /// # Panics
+    /// Panics if the `StackAutomaton` is poisoned. Use [`recover_with()`](#method.recover_with) instead.
+    /// 
+    pub fn push(automaton : &mut Self, mode : F::Mode) {
+        assert!(!automaton.poisoned, "Cannot push a Mode because the StackAutomaton is poisoned!");
+        automaton.modes.push(mode);
+    }
+
+    /// Pops the current `Mode` off the top of the stack and returns it, making the `Mode` beneath it current. Returns
+    /// `None` if the stack is empty.
+    /// 
+    /// # Panics
+    /// Panics if the `StackAutomaton` is poisoned, since there is no current `Mode` to pop.
+    /// 
+    pub fn pop(automaton : &mut Self) -> Option<F::Mode> {
+        assert!(!automaton.poisoned, "Cannot pop the current Mode because the StackAutomaton is poisoned!");
+        automaton.modes.pop()
+    }
+
+    /// Returns the number of `Mode`s on the stack.
+    /// 
+    pub fn len(automaton : &Self) -> usize {
+        automaton.modes.len()
+    }
+
+    /// Returns `true` if all `Mode`s have been popped off the stack.
+    /// 
+    pub fn is_empty(automaton : &Self) -> bool {
+        automaton.modes.is_empty()
+    }
+
+    /// Returns `true` if a transition function passed into [`next()`](#method.next) (or
+    /// [`next_with_result()`](#method.next_with_result)) panicked, taking the current `Mode` with it. The `Mode`s
+    /// beneath it are still on the stack, but none of them becomes current. Instead, a poisoned `StackAutomaton` panics
+    /// if `borrow_mode()`, `borrow_mode_mut()`, `next()`, `push()`, or `pop()` is called on it, and `try_borrow_mode()`
+    /// returns a `PoisonError`, until it is repaired via [`recover_with()`](#method.recover_with).
+    /// 
+    /// # Usage
+    /// ```
+    /// use mode::*;
+    /// use std::panic::{self, AssertUnwindSafe};
+    /// 
+    /// struct SomeFamily;
+    /// impl Family for SomeFamily {
+    ///     type Base = SomeMode;
+    ///     type Mode = SomeMode;
+    /// }
+    /// 
+    /// #[derive(Debug, Eq, PartialEq)]
+    /// enum SomeMode { A, B, C }
+    /// impl Mode for SomeMode { type Family = SomeFamily; }
+    /// 
+    /// let mut automaton = StackAutomaton::<SomeFamily>::with_mode(SomeMode::A);
+    /// StackAutomaton::push(&mut automaton, SomeMode::B);
+    /// 
+    /// // Panic in the middle of a transition, and catch the panic.
+    /// let result = panic::catch_unwind(AssertUnwindSafe(|| {
+    ///     StackAutomaton::next(&mut automaton, |_| panic!("Something went wrong!"));
+    /// }));
+    /// assert!(result.is_err());
+    /// assert!(StackAutomaton::is_poisoned(&automaton));
+    /// assert!(automaton.try_borrow_mode().is_err());
+    /// 
+    /// // Repair the StackAutomaton by putting a new Mode on top of the stack, where B used to be.
+    /// assert!(StackAutomaton::recover_with(&mut automaton, SomeMode::C).is_ok());
+    /// assert_eq!(*automaton, SomeMode::C);
+    /// assert_eq!(StackAutomaton::len(&automaton), 2);
+    /// ```
+    /// 
+    pub fn is_poisoned(automaton : &Self) -> bool {
+        automaton.poisoned
+    }
+
+    /// Repairs a poisoned `StackAutomaton` by pushing `mode` onto the top of the stack in place of the `Mode` that was
+    /// lost, making it current, and returning `Ok(())`. If the `StackAutomaton` is **not** poisoned, the stack is left
+    /// untouched and `mode` is handed back to the caller as an `Err`.
+    /// 
+    /// See [`is_poisoned()`](#method.is_poisoned) for more details.
+    /// 
+    pub fn recover_with(automaton : &mut Self, mode : F::Mode) -> Result<(), F::Mode> {
+        if !automaton.poisoned {
+            return Err(mode);
+        }
+
+        automaton.modes.push(mode);
+        automaton.poisoned = false;
+        Ok(())
+    }
+}
+
+impl<F> StackAutomaton<F>
+    where
+        F : Family + ?Sized,
+        F::Mode : Borrow<F::Base>,
+{
+    /// Returns an immutable reference to the `Mode` on top of the stack as an `&F::Base`.
+    /// 
+    /// **NOTE:** `StackAutomaton` also implements `Deref<Target = F::Base>`, so you can usually leave the
+    /// `borrow_mode()` out and simply treat the `StackAutomaton` as if it were an object of type `Base`.
+    /// 
+    pub fn borrow_mode(&self) -> &F::Base {
+        self.try_borrow_mode()
+            .expect("Cannot borrow current Mode because the StackAutomaton is poisoned!")
+            .expect("Cannot borrow current Mode because the stack is empty!")
+    }
+
+    /// Returns an immutable reference to the `Mode` on top of the stack as an `&F::Base`, `Ok(None)` if the stack is
+    /// empty, or a `PoisonError` if a previous transition function panicked and left the `StackAutomaton` without a
+    /// current `Mode`.
+    /// 
+    /// See [`is_poisoned()`](#method.is_poisoned) for more details.
+    /// 
+    pub fn try_borrow_mode(&self) -> Result<Option<&F::Base>, PoisonError> {
+        if self.poisoned {
+            return Err(PoisonError::new());
+        }
+        Ok(self.modes.last().map(Borrow::borrow))
+    }
+
+    /// Returns an iterator over all `Mode`s on the stack as `&F::Base` references, from the bottom of the stack to the
+    /// top.
+    /// 
+    pub fn iter(automaton : &Self) -> impl Iterator<Item = &F::Base> {
+        automaton.modes.iter().map(Borrow::borrow)
+    }
+}
+
+impl<F> StackAutomaton<F>
+    where
+        F : Family + ?Sized,
+        F::Mode : BorrowMut<F::Base>,
+{
+    /// Returns a mutable reference to the `Mode` on top of the stack as a `&mut F::Base`.
+    /// 
+    /// **NOTE:** `StackAutomaton` also implements `DerefMut<Target = F::Base>`, so you can usually leave the
+    /// `borrow_mode_mut()` out and simply treat the `StackAutomaton` as if it were an object of type `Base`.
+    /// 
+    pub fn borrow_mode_mut(&mut self) -> &mut F::Base {
+        self.try_borrow_mode_mut()
+            .expect("Cannot borrow current Mode because the StackAutomaton is poisoned!")
+            .expect("Cannot borrow current Mode because the stack is empty!")
+    }
+
+    /// Returns a mutable reference to the `Mode` on top of the stack as a `&mut F::Base`, `Ok(None)` if the stack is
+    /// empty, or a `PoisonError` if a previous transition function panicked and left the `StackAutomaton` without a
+    /// current `Mode`.
+    /// 
+    /// See [`is_poisoned()`](#method.is_poisoned) for more details.
+    /// 
+    pub fn try_borrow_mode_mut(&mut self) -> Result<Option<&mut F::Base>, PoisonError> {
+        if self.poisoned {
+            return Err(PoisonError::new());
+        }
+        Ok(self.modes.last_mut().map(BorrowMut::borrow_mut))
+    }
+}
+
+impl<F> StackAutomaton<F>
+    where
+        F : Family + ?Sized,
+        F::Mode : Default,
+{
+    /// Creates a new `StackAutomaton` with a default `Mode` instance as the only `Mode` on the stack.
+    /// 
+    pub fn new() -> Self {
+        Self::with_mode(Default::default())
+    }
+}
+
+impl<F> Default for StackAutomaton<F>
+    where
+        F : Family + ?Sized,
+        F::Mode : Default,
+{
+    /// Creates a new `StackAutomaton` with the default `Mode` on the stack. This is equivalent to calling
+    /// `StackAutomaton::new()`.
+    /// 
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F> Deref for StackAutomaton<F>
+    where
+        F : Family + ?Sized,
+        F::Mode : Borrow<F::Base>,
+{
+    type Target = F::Base;
+
+    /// Returns an immutable reference to the `Mode` on top of the stack as a `&F::Base`.
+    /// 
+    fn deref(&self) -> &F::Base {
+        self.borrow_mode()
+    }
+}
+
+impl<F> DerefMut for StackAutomaton<F>
+    where
+        F : Family + ?Sized,
+        F::Mode : Borrow<F::Base> + BorrowMut<F::Base>,
+{
+    /// Returns a mutable reference to the `Mode` on top of the stack as a `&mut F::Base`.
+    /// 
+    fn deref_mut(&mut self) -> &mut F::Base {
+        self.borrow_mode_mut()
+    }
+}
+
+/// If `Base` implements `std::fmt::Debug`, `StackAutomaton` also implements `Debug`, and will print all `Mode`s on the
+/// stack, from bottom to top.
+/// 
+impl<F> fmt::Debug for StackAutomaton<F>
+    where
+        F : Family + ?Sized,
+        F::Mode : Borrow<F::Base>,
+        F::Base : fmt::Debug,
+{
+    fn fmt(&self, formatter : &mut fmt::Formatter) -> fmt::Result {
+        formatter.debug_struct("StackAutomaton")
+            .field("modes", &self.modes.iter().map(Borrow::borrow).collect::<Vec<&F::Base>>())
+            .field("poisoned", &self.poisoned)
+            .finish()
+    }
+}
@@ -0,0 +1,403 @@
+// Copyright 2019 Andrew Thomas Christensen
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the
+// MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+use crate::{Automaton, Clock, Family, ModeId, NamedMode, PoisonError};
+use alloc::vec::Vec;
+use core::{borrow::{Borrow, BorrowMut}, ops::Deref, time::Duration};
+
+/// Statistics collected by a [`Profiler`](struct.Profiler.html) for a single `Mode`.
+/// 
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct ModeStatistics {
+    /// The number of times the `Mode` was entered, including the initial `Mode`.
+    /// 
+    pub entries : u64,
+
+    /// The total time spent in the `Mode`, over all **completed** visits. Time spent in the current `Mode` is not
+    /// included until it is swapped out. See
+    /// [`Profiler::time_in_current_mode()`](struct.Profiler.html#method.time_in_current_mode).
+    /// 
+    pub total_time : Duration,
+
+    /// The longest time spent in the `Mode` during a single **completed** visit.
+    /// 
+    pub max_time : Duration,
+}
+
+/// Per-`Mode` statistics and a histogram of transitions between `Mode`s, as collected by a
+/// [`Profiler`](struct.Profiler.html). Each `Mode` is identified by a key of type `K`.
+/// 
+/// Keys only need to implement `PartialEq`, so that e.g. `std::mem::Discriminant` can be used as a key. `Mode`s and
+/// transitions are listed in the order they were first seen.
+/// 
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Statistics<K> {
+    modes : Vec<(K, ModeStatistics)>,
+    transitions : Vec<(usize, usize, u64)>,
+}
+
+impl<K> Statistics<K> {
+    /// Creates a new, empty `Statistics` object.
+    /// 
+    pub fn new() -> Self {
+        Self { modes: Vec::new(), transitions: Vec::new() }
+    }
+
+    /// Returns the statistics for each `Mode` that has been entered, along with its key.
+    /// 
+    pub fn modes(&self) -> impl Iterator<Item = (&K, &ModeStatistics)> {
+        self.modes.iter().map(|(key, statistics)| (key, statistics))
+    }
+
+    /// Returns each transition that has been taken, as a tuple of the `from` key, the `to` key, and the number of times
+    /// the transition was taken.
+    /// 
+    pub fn transitions(&self) -> impl Iterator<Item = (&K, &K, u64)> {
+        self.transitions.iter().map(move |&(from, to, count)| (&self.modes[from].0, &self.modes[to].0, count))
+    }
+
+    /// Returns the total number of transitions that have been taken.
+    /// 
+    pub fn transition_total(&self) -> u64 {
+        self.transitions.iter().map(|&(_, _, count)| count).sum()
+    }
+}
+
+impl<K> Statistics<K>
+    where K : PartialEq
+{
+    /// Returns the statistics for the `Mode` with the specified `key`, or `None` if it has never been entered.
+    /// 
+    pub fn mode(&self, key : &K) -> Option<&ModeStatistics> {
+        self.modes.iter().find(|(k, _)| k == key).map(|(_, statistics)| statistics)
+    }
+
+    /// Returns the number of times the transition from the `Mode` with key `from` to the `Mode` with key `to` was
+    /// taken.
+    /// 
+    pub fn transition_count(&self, from : &K, to : &K) -> u64 {
+        let from = self.modes.iter().position(|(key, _)| key == from);
+        let to = self.modes.iter().position(|(key, _)| key == to);
+        self.transitions.iter()
+            .find(|&&(f, t, _)| Some(f) == from && Some(t) == to)
+            .map_or(0, |&(_, _, count)| count)
+    }
+
+    fn index_of(&mut self, key : K) -> usize {
+        match self.modes.iter().position(|(k, _)| *k == key) {
+            Some(index) => index,
+            None => {
+                self.modes.push((key, ModeStatistics::default()));
+                self.modes.len() - 1
+            },
+        }
+    }
+
+    fn enter(&mut self, key : K) -> usize {
+        let index = self.index_of(key);
+        self.modes[index].1.entries += 1;
+        index
+    }
+
+    fn transition(&mut self, from : K, to : K, time : Duration) {
+        let from = self.index_of(from);
+        let statistics = &mut self.modes[from].1;
+        statistics.total_time += time;
+        statistics.max_time = statistics.max_time.max(time);
+
+        let to = self.enter(to);
+        match self.transitions.iter_mut().find(|(f, t, _)| *f == from && *t == to) {
+            Some((_, _, count)) => *count += 1,
+            None => self.transitions.push((from, to, 1)),
+        }
+    }
+}
+
+impl<K> Default for Statistics<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A wrapper around an `Automaton` that collects [`Statistics`](struct.Statistics.html) about it, i.e. how many times
+/// each `Mode` was entered, how long the `Automaton` spent in each `Mode`, and how many times each transition between
+/// `Mode`s was taken. Time is measured by a [`Clock`](trait.Clock.html) of type `C`, so a
+/// [`ManualClock`](struct.ManualClock.html) can be used to make tests deterministic.
+/// 
+/// Just like a [`Recorder`](struct.Recorder.html), a `Profiler` identifies each `Mode` by a **key**. By default, this
+/// is the [`ModeId`](struct.ModeId.html) of the `Mode`, so `F::Base` must implement
+/// [`NamedMode`](trait.NamedMode.html). To use some other key, e.g. the `discriminant()` of an `enum`, create the
+/// `Profiler` via [`with_key_fn()`](#method.with_key_fn) instead. Every transition is counted, including
+/// self-transitions where the key of the `Mode` doesn't change, which count as leaving the `Mode` and entering it
+/// again. To only count transitions that change the key, call
+/// [`skip_self_transitions()`](#method.skip_self_transitions). In order for all transitions to be counted, they must go
+/// through [`Profiler::next()`](#method.next) or [`Profiler::next_with_result()`](#method.next_with_result).
+/// 
+/// # Usage
+/// ```
+/// use mode::*;
+/// use std::{rc::Rc, time::Duration};
+/// 
+/// struct LightFamily;
+/// impl Family for LightFamily {
+///     type Base = Light;
+///     type Mode = Light;
+/// }
+/// 
+/// enum Light { Red, Green, Yellow }
+/// impl Mode for Light { type Family = LightFamily; }
+/// 
+/// impl NamedMode for Light {
+///     fn mode_name(&self) -> &'static str {
+///         match self {
+///             Light::Red => "Red",
+///             Light::Green => "Green",
+///             Light::Yellow => "Yellow",
+///         }
+///     }
+/// }
+/// 
+/// fn cycle(light : Light) -> Light {
+///     match light {
+///         Light::Red => Light::Green,
+///         Light::Green => Light::Yellow,
+///         Light::Yellow => Light::Red,
+///     }
+/// }
+/// 
+/// let clock = Rc::new(ManualClock::new());
+/// let automaton = LightFamily::automaton_with_mode(Light::Red);
+/// let mut profiler = Profiler::new(automaton, clock.clone());
+/// 
+/// for seconds in vec![30, 25, 5, 40] {
+///     clock.advance(Duration::from_secs(seconds));
+///     Profiler::next(&mut profiler, cycle);
+/// }
+/// 
+/// let statistics = Profiler::statistics(&profiler);
+/// let red = statistics.mode(&ModeId::new::<Light>("Red")).unwrap();
+/// assert_eq!(red.entries, 2);
+/// assert_eq!(red.total_time, Duration::from_secs(70));
+/// assert_eq!(red.max_time, Duration::from_secs(40));
+/// assert_eq!(statistics.transition_count(&ModeId::new::<Light>("Yellow"), &ModeId::new::<Light>("Red")), 1);
+/// assert_eq!(statistics.transition_total(), 4);
+/// 
+/// // The current visit to Green isn't included in the statistics until it ends.
+/// clock.advance(Duration::from_secs(10));
+/// assert_eq!(Profiler::time_in_current_mode(&profiler), Duration::from_secs(10));
+/// let green = ModeId::new::<Light>("Green");
+/// assert_eq!(Profiler::statistics(&profiler).mode(&green).unwrap().max_time, Duration::from_secs(25));
+/// 
+/// // Resetting the Profiler starts collecting from scratch.
+/// Profiler::reset(&mut profiler);
+/// assert_eq!(Profiler::statistics(&profiler).transition_total(), 0);
+/// ```
+/// 
+pub struct Profiler<F, K, KeyFn, C>
+    where F : Family + ?Sized
+{
+    automaton : Automaton<F>,
+    key_fn : KeyFn,
+    clock : C,
+    entered_at : Duration,
+    statistics : Statistics<K>,
+    skip_self_transitions : bool,
+}
+
+impl<F, C> Profiler<F, ModeId, fn(&F::Base) -> ModeId, C>
+    where
+        F : Family + ?Sized,
+        F::Mode : Borrow<F::Base>,
+        F::Base : NamedMode,
+        C : Clock,
+{
+    /// Creates a new `Profiler` wrapping `automaton`, measuring time with `clock`. Each `Mode` will be identified by
+    /// its `ModeId`, as returned from [`NamedMode::mode_id()`](trait.NamedMode.html#method.mode_id). The current `Mode`
+    /// of `automaton` is counted as entered at this point.
+    /// 
+    pub fn new(automaton : Automaton<F>, clock : C) -> Self {
+        Self::with_key_fn(automaton, <F::Base as NamedMode>::mode_id, clock)
+    }
+}
+
+impl<F, K, KeyFn, C> Profiler<F, K, KeyFn, C>
+    where
+        F : Family + ?Sized,
+        F::Mode : Borrow<F::Base>,
+        K : PartialEq,
+        KeyFn : Fn(&F::Base) -> K,
+        C : Clock,
+{
+    /// Creates a new `Profiler` wrapping `automaton`, measuring time with `clock`. Each `Mode` will be identified by
+    /// the key returned from `key_fn`. The current `Mode` of `automaton` is counted as entered at this point.
+    /// 
+    /// ```
+    /// use mode::*;
+    /// use std::mem::discriminant;
+    /// 
+    /// struct LightFamily;
+    /// impl Family for LightFamily {
+    ///     type Base = Light;
+    ///     type Mode = Light;
+    /// }
+    /// 
+    /// enum Light { Red, Green }
+    /// impl Mode for Light { type Family = LightFamily; }
+    /// 
+    /// let clock = ManualClock::new();
+    /// let automaton = LightFamily::automaton_with_mode(Light::Red);
+    /// let mut profiler = Profiler::with_key_fn(automaton, |light : &Light| discriminant(light), &clock);
+    /// Profiler::next(&mut profiler, |_| Light::Green);
+    /// 
+    /// let statistics = Profiler::statistics(&profiler);
+    /// assert_eq!(statistics.transition_count(&discriminant(&Light::Red), &discriminant(&Light::Green)), 1);
+    /// ```
+    /// 
+    pub fn with_key_fn(automaton : Automaton<F>, key_fn : KeyFn, clock : C) -> Self {
+        let mut profiler = Self {
+            automaton,
+            key_fn,
+            clock,
+            entered_at: Duration::default(),
+            statistics: Statistics::new(),
+            skip_self_transitions: false,
+        };
+        Self::reset(&mut profiler);
+        profiler
+    }
+
+    /// Calls `transition_fn` on the current `Mode`, swapping in whatever `Mode` it returns, just like
+    /// [`Automaton::next()`](struct.Automaton.html#method.next), and counts the transition in the `Statistics`.
+    /// 
+    pub fn next<T>(profiler : &mut Self, transition_fn : T)
+        where T : FnOnce(F::Mode) -> F::Mode
+    {
+        Self::next_with_result(profiler, |current_mode| (transition_fn(current_mode), ()))
+    }
+
+    /// Calls `transition_fn` on the current `Mode`, swapping in the `Mode` it returns and returning the result, just
+    /// like [`Automaton::next_with_result()`](struct.Automaton.html#method.next_with_result), and counts the transition
+    /// in the `Statistics`.
+    /// 
+    pub fn next_with_result<T, R>(profiler : &mut Self, transition_fn : T) -> R
+        where T : FnOnce(F::Mode) -> (F::Mode, R)
+    {
+        let before = (profiler.key_fn)(profiler.automaton.borrow_mode());
+        let result = Automaton::next_with_result(&mut profiler.automaton, transition_fn);
+        let after = (profiler.key_fn)(profiler.automaton.borrow_mode());
+
+        if profiler.skip_self_transitions && before == after {
+            return result;
+        }
+
+        let now = profiler.clock.now();
+        let time = now.checked_sub(profiler.entered_at).unwrap_or_default();
+        profiler.entered_at = now;
+        profiler.statistics.transition(before, after, time);
+
+        result
+    }
+
+    /// Sets whether transitions that don't change the key of the current `Mode` should be skipped. By default, every
+    /// transition is counted, so a `Mode` that returns itself from a transition function counts as a visit to that
+    /// `Mode` and a self-loop in the `Statistics`, and the time spent in the `Mode` starts over. If `skip` is `true`,
+    /// such transitions are ignored instead, so the `Statistics` only count changes of the key.
+    /// 
+    /// ```
+    /// use mode::*;
+    /// use std::mem::discriminant;
+    /// 
+    /// struct LightFamily;
+    /// impl Family for LightFamily {
+    ///     type Base = Light;
+    ///     type Mode = Light;
+    /// }
+    /// 
+    /// enum Light { Red, Green }
+    /// impl Mode for Light { type Family = LightFamily; }
+    /// 
+    /// let red = discriminant(&Light::Red);
+    /// 
+    /// let automaton = LightFamily::automaton_with_mode(Light::Red);
+    /// let mut profiler = Profiler::with_key_fn(automaton, |light : &Light| discriminant(light), ManualClock::new());
+    /// Profiler::next(&mut profiler, |light| light);
+    /// assert_eq!(Profiler::statistics(&profiler).mode(&red).unwrap().entries, 2);
+    /// assert_eq!(Profiler::statistics(&profiler).transition_count(&red, &red), 1);
+    /// 
+    /// Profiler::skip_self_transitions(&mut profiler, true);
+    /// Profiler::next(&mut profiler, |light| light);
+    /// assert_eq!(Profiler::statistics(&profiler).mode(&red).unwrap().entries, 2);
+    /// assert_eq!(Profiler::statistics(&profiler).transition_count(&red, &red), 1);
+    /// ```
+    /// 
+    pub fn skip_self_transitions(profiler : &mut Self, skip : bool) {
+        profiler.skip_self_transitions = skip;
+    }
+
+    /// Returns the `Statistics` collected so far.
+    /// 
+    pub fn statistics(profiler : &Self) -> &Statistics<K> {
+        &profiler.statistics
+    }
+
+    /// Returns how long the `Automaton` has been in the current `Mode`, according to the `Clock`.
+    /// 
+    pub fn time_in_current_mode(profiler : &Self) -> Duration {
+        profiler.clock.now().checked_sub(profiler.entered_at).unwrap_or_default()
+    }
+
+    /// Clears all `Statistics` collected so far. The current `Mode` is counted as entered at this point.
+    /// 
+    pub fn reset(profiler : &mut Self) {
+        profiler.statistics = Statistics::new();
+        profiler.statistics.enter((profiler.key_fn)(profiler.automaton.borrow_mode()));
+        profiler.entered_at = profiler.clock.now();
+    }
+}
+
+impl<F, K, KeyFn, C> Profiler<F, K, KeyFn, C>
+    where F : Family + ?Sized
+{
+    /// Consumes the `Profiler`, returning the wrapped `Automaton` and the `Statistics` collected so far.
+    /// 
+    pub fn into_parts(profiler : Self) -> (Automaton<F>, Statistics<K>) {
+        (profiler.automaton, profiler.statistics)
+    }
+}
+
+impl<F, K, KeyFn, C> Profiler<F, K, KeyFn, C>
+    where
+        F : Family + ?Sized,
+        F::Mode : BorrowMut<F::Base>,
+{
+    /// Returns a mutable reference to the current `Mode` as a `&mut F::Base`, just like
+    /// [`Automaton::borrow_mode_mut()`](struct.Automaton.html#method.borrow_mode_mut).
+    /// This is not a transition, so the `Statistics` are not updated.
+    /// 
+    pub fn borrow_mode_mut(profiler : &mut Self) -> &mut F::Base {
+        profiler.automaton.borrow_mode_mut()
+    }
+
+    /// Returns a mutable reference to the current `Mode` as a `&mut F::Base`, or a `PoisonError` if the wrapped
+    /// `Automaton` is poisoned, just like
+    /// [`Automaton::try_borrow_mode_mut()`](struct.Automaton.html#method.try_borrow_mode_mut).
+    /// 
+    pub fn try_borrow_mode_mut(profiler : &mut Self) -> Result<&mut F::Base, PoisonError> {
+        profiler.automaton.try_borrow_mode_mut()
+    }
+}
+
+impl<F, K, KeyFn, C> Deref for Profiler<F, K, KeyFn, C>
+    where F : Family + ?Sized
+{
+    type Target = Automaton<F>;
+
+    /// Returns a reference to the wrapped `Automaton`.
+    /// 
+    fn deref(&self) -> &Automaton<F> {
+        &self.automaton
+    }
+}
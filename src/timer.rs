@@ -0,0 +1,300 @@
+// Copyright 2019 Andrew Thomas Christensen
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the
+// MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+use crate::{Automaton, Clock, Family, HandlesEvent, Mode, PoisonError, Response};
+use alloc::vec::Vec;
+use core::{borrow::BorrowMut, ops::Deref, time::Duration};
+
+/// The set of pending timeouts for the current `Mode` of a [`TimedAutomaton`](struct.TimedAutomaton.html). This is
+/// passed into [`HandlesTimeout::schedule_timeouts()`](trait.HandlesTimeout.html#method.schedule_timeouts) whenever a
+/// new `Mode` is entered, so that the `Mode` can schedule any timeouts it needs.
+/// 
+pub struct Timers<T> {
+    now : Duration,
+    next_sequence : u64,
+    pending : Vec<(Duration, u64, T)>,
+}
+
+impl<T> Timers<T> {
+    fn new() -> Self {
+        Self { now: Duration::from_secs(0), next_sequence: 0, pending: Vec::new() }
+    }
+
+    /// Schedules `timer` to be delivered to the current `Mode` once it has been active for `after`, unless it is
+    /// swapped out first. Timers with the same deadline are delivered in the order they were scheduled.
+    /// 
+    pub fn schedule(&mut self, after : Duration, timer : T) {
+        self.pending.push((self.now + after, self.next_sequence, timer));
+        self.next_sequence += 1;
+    }
+
+    /// Returns the number of timers that are still pending.
+    /// 
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Returns `true` if there are no pending timers.
+    /// 
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+/// Trait for a `Mode` that schedules timeouts of type `T` when it becomes active in a
+/// [`TimedAutomaton`](struct.TimedAutomaton.html), and transitions when they expire.
+/// 
+/// Like [`HandlesEvent`](trait.HandlesEvent.html), this is implemented on the `Mode` type of the `Family`, e.g.
+/// `Box<dyn Trait>`, so that `handle_timeout()` can return any `Mode` in the `Family` to switch to.
+/// 
+pub trait HandlesTimeout<T> : Mode + Sized {
+    /// Called on a `Mode` right after it becomes the current `Mode` of a `TimedAutomaton`, in order to schedule any
+    /// timeouts via `timers`. The default implementation doesn't schedule any.
+    /// 
+    fn schedule_timeouts(&self, _timers : &mut Timers<T>) { }
+
+    /// Called on the current `Mode` when one of the timeouts it scheduled expires. Returns a `Response` indicating
+    /// whether the timeout was handled, and whether the current `Mode` should be swapped out as a result.
+    /// 
+    fn handle_timeout(&mut self, timer : T) -> Response<Self>;
+}
+
+/// A wrapper around an `Automaton` that allows each `Mode` to schedule timeouts of type `T` when it is entered, e.g.
+/// "if we stay in this state for 5 seconds, go to `Timeout`". The `Mode` type of the `Family` must implement
+/// [`HandlesTimeout<T>`](trait.HandlesTimeout.html).
+/// 
+/// Time is measured by a [`Clock`](trait.Clock.html) of type `C`, which is usually an
+/// [`InstantClock`](struct.InstantClock.html) in production and a shared [`ManualClock`](struct.ManualClock.html) in
+/// tests.
+/// 
+/// # Semantics
+/// Whenever a new `Mode` is entered, i.e. on creation and after every transition that returns `Response::Switch`, all
+/// pending timers are cancelled and `schedule_timeouts()` is called on the new `Mode`. A transition that returns
+/// `Response::Handled` or `Response::Unhandled` leaves the current `Mode`, and therefore its timers, in place.
+/// 
+/// Expired timers are **only** delivered when [`update()`](#method.update) is called, in order of their deadlines, by
+/// passing them into [`HandlesTimeout::handle_timeout()`](trait.HandlesTimeout.html#tymethod.handle_timeout) via
+/// `Automaton::next_with_result()`, just like any other transition. All other transitions should go through
+/// [`next_with_response()`](#method.next_with_response) or [`handle()`](#method.handle), so that timers are
+/// rescheduled correctly.
+/// 
+/// # Usage
+/// ```
+/// use mode::*;
+/// use std::{rc::Rc, time::Duration};
+/// 
+/// struct ConnectionFamily;
+/// impl Family for ConnectionFamily {
+///     type Base = Connection;
+///     type Mode = Connection;
+/// }
+/// 
+/// #[derive(Debug, Eq, PartialEq)]
+/// enum Connection { Connecting, Connected, TimedOut }
+/// impl Mode for Connection { type Family = ConnectionFamily; }
+/// 
+/// struct GiveUp;
+/// 
+/// impl HandlesTimeout<GiveUp> for Connection {
+///     fn schedule_timeouts(&self, timers : &mut Timers<GiveUp>) {
+///         if *self == Connection::Connecting {
+///             timers.schedule(Duration::from_secs(5), GiveUp);
+///         }
+///     }
+/// 
+///     fn handle_timeout(&mut self, _ : GiveUp) -> Response<Self> {
+///         Response::Switch(Connection::TimedOut)
+///     }
+/// }
+/// 
+/// struct Handshake;
+/// 
+/// impl HandlesEvent<Handshake> for Connection {
+///     fn on_event(&mut self, _ : Handshake) -> Response<Self> {
+///         match self {
+///             Connection::Connecting => Response::Switch(Connection::Connected),
+///             _ => Response::Unhandled,
+///         }
+///     }
+/// }
+/// 
+/// let clock = Rc::new(ManualClock::new());
+/// 
+/// // If we stay Connecting for 5 seconds, we time out.
+/// let mut timed = TimedAutomaton::new(ConnectionFamily::automaton_with_mode(Connection::Connecting), clock.clone());
+/// clock.advance(Duration::from_secs(4));
+/// assert_eq!(TimedAutomaton::update(&mut timed), 0);
+/// clock.advance(Duration::from_secs(1));
+/// assert_eq!(TimedAutomaton::update(&mut timed), 1);
+/// assert_eq!(**timed, Connection::TimedOut);
+/// 
+/// // Leaving Connecting before then cancels the timeout.
+/// let mut timed = TimedAutomaton::new(ConnectionFamily::automaton_with_mode(Connection::Connecting), clock.clone());
+/// clock.advance(Duration::from_secs(3));
+/// assert_eq!(TimedAutomaton::handle(&mut timed, Handshake), Response::Switch(()));
+/// clock.advance(Duration::from_secs(10));
+/// assert_eq!(TimedAutomaton::update(&mut timed), 0);
+/// assert_eq!(**timed, Connection::Connected);
+/// ```
+/// 
+pub struct TimedAutomaton<F, T, C>
+    where F : Family + ?Sized
+{
+    automaton : Automaton<F>,
+    timers : Timers<T>,
+    clock : C,
+}
+
+impl<F, T, C> TimedAutomaton<F, T, C>
+    where
+        F : Family + ?Sized,
+        F::Mode : HandlesTimeout<T>,
+        C : Clock,
+{
+    /// Creates a new `TimedAutomaton` wrapping `automaton`, measuring time with `clock`. The current `Mode` of
+    /// `automaton` is considered to be entered at this point, so `schedule_timeouts()` is called on it immediately.
+    /// 
+    pub fn new(automaton : Automaton<F>, clock : C) -> Self {
+        let mut timed = Self { automaton, timers: Timers::new(), clock };
+        Self::enter(&mut timed);
+        timed
+    }
+
+    /// Delivers every timer whose deadline has passed according to the `Clock`, in order of their deadlines, and
+    /// returns the number of timers that were delivered. This should be called regularly, e.g. once per frame, or
+    /// after sleeping for [`time_until_next_timeout()`](#method.time_until_next_timeout).
+    /// 
+    /// Timers scheduled by a `Mode` that was entered during this call are not delivered until the next call, even if
+    /// they have already expired, so that a `Mode` scheduling a zero-length timeout can't cause an infinite loop.
+    /// 
+    pub fn update(timed : &mut Self) -> usize {
+        let first_unscheduled = timed.timers.next_sequence;
+        let mut delivered = 0;
+
+        loop {
+            let now = timed.clock.now();
+            let expired = timed.timers.pending.iter()
+                .enumerate()
+                .filter(|(_, &(deadline, sequence, _))| deadline <= now && sequence < first_unscheduled)
+                .min_by_key(|(_, &(deadline, sequence, _))| (deadline, sequence))
+                .map(|(index, _)| index);
+
+            let index = match expired {
+                Some(index) => index,
+                None => break,
+            };
+
+            let (_, _, timer) = timed.timers.pending.remove(index);
+            Self::next_with_response(timed, |current_mode| current_mode.handle_timeout(timer));
+            delivered += 1;
+        }
+
+        delivered
+    }
+
+    /// Calls `transition_fn` on the current `Mode`, swapping in the `Mode` from `Response::Switch`, if any, via
+    /// [`Automaton::next_with_result()`](struct.Automaton.html#method.next_with_result). If a different `Mode` was
+    /// swapped in, all pending timers are cancelled and the new `Mode` gets to schedule its own. Returns the
+    /// `Response`.
+    /// 
+    pub fn next_with_response<TransitionFn>(timed : &mut Self, transition_fn : TransitionFn) -> Response
+        where TransitionFn : FnOnce(&mut F::Mode) -> Response<F::Mode>
+    {
+        let response = Automaton::next_with_result(&mut timed.automaton, |mut current_mode| {
+            transition_fn(&mut current_mode).swap(current_mode)
+        });
+
+        if response == Response::Switch(()) {
+            Self::enter(timed);
+        }
+        response
+    }
+
+    /// Passes `event` into the current `Mode` via [`HandlesEvent::on_event()`](trait.HandlesEvent.html), just like
+    /// [`Automaton::handle()`](struct.Automaton.html#method.handle), rescheduling timers if the `Mode` switched.
+    /// 
+    pub fn handle<E>(timed : &mut Self, event : E) -> Response
+        where F::Mode : HandlesEvent<E>
+    {
+        Self::next_with_response(timed, |current_mode| current_mode.on_event(event))
+    }
+
+    /// Returns how long it will be until the earliest pending timer expires, or `None` if there are no pending timers.
+    /// Returns a zero `Duration` if a timer has already expired.
+    /// 
+    pub fn time_until_next_timeout(timed : &Self) -> Option<Duration> {
+        let now = timed.clock.now();
+        timed.timers.pending.iter()
+            .map(|&(deadline, _, _)| deadline)
+            .min()
+            .map(|deadline| deadline.checked_sub(now).unwrap_or_default())
+    }
+
+    fn enter(timed : &mut Self) {
+        let current_mode = timed.automaton.mode.as_ref()
+            .expect("Cannot schedule timeouts because the Automaton is poisoned!");
+
+        timed.timers.pending.clear();
+        timed.timers.now = timed.clock.now();
+        current_mode.schedule_timeouts(&mut timed.timers);
+    }
+}
+
+impl<F, T, C> TimedAutomaton<F, T, C>
+    where F : Family + ?Sized
+{
+    /// Returns the number of timers that are still pending for the current `Mode`.
+    /// 
+    pub fn pending_timeouts(timed : &Self) -> usize {
+        timed.timers.len()
+    }
+
+    /// Returns a reference to the `Clock` used to measure time.
+    /// 
+    pub fn clock(timed : &Self) -> &C {
+        &timed.clock
+    }
+
+    /// Consumes the `TimedAutomaton`, returning the wrapped `Automaton` and `Clock`. Any pending timers are cancelled.
+    /// 
+    pub fn into_parts(timed : Self) -> (Automaton<F>, C) {
+        (timed.automaton, timed.clock)
+    }
+}
+
+impl<F, T, C> TimedAutomaton<F, T, C>
+    where
+        F : Family + ?Sized,
+        F::Mode : BorrowMut<F::Base>,
+{
+    /// Returns a mutable reference to the current `Mode` as a `&mut F::Base`, just like
+    /// [`Automaton::borrow_mode_mut()`](struct.Automaton.html#method.borrow_mode_mut).
+    /// This is not a transition, so no timers are cancelled or scheduled.
+    /// 
+    pub fn borrow_mode_mut(timed : &mut Self) -> &mut F::Base {
+        timed.automaton.borrow_mode_mut()
+    }
+
+    /// Returns a mutable reference to the current `Mode` as a `&mut F::Base`, or a `PoisonError` if the wrapped
+    /// `Automaton` is poisoned, just like
+    /// [`Automaton::try_borrow_mode_mut()`](struct.Automaton.html#method.try_borrow_mode_mut).
+    /// 
+    pub fn try_borrow_mode_mut(timed : &mut Self) -> Result<&mut F::Base, PoisonError> {
+        timed.automaton.try_borrow_mode_mut()
+    }
+}
+
+impl<F, T, C> Deref for TimedAutomaton<F, T, C>
+    where F : Family + ?Sized
+{
+    type Target = Automaton<F>;
+
+    /// Returns a reference to the wrapped `Automaton`.
+    /// 
+    fn deref(&self) -> &Automaton<F> {
+        &self.automaton
+    }
+}
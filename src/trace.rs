@@ -0,0 +1,194 @@
+// Copyright 2019 Andrew Thomas Christensen
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the
+// MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+use crate::{Automaton, Family, NamedMode};
+use core::{any::type_name, borrow::Borrow};
+use tracing::{Level, Span};
+
+pub(crate) struct Tracer<F>
+    where F : Family + ?Sized
+{
+    name_fn : fn(&F::Base) -> &'static str,
+    borrow_fn : fn(&F::Mode) -> &F::Base,
+    mode_spans : bool,
+    span : Span,
+    outgoing : &'static str,
+}
+
+impl<F> Tracer<F>
+    where F : Family + ?Sized
+{
+    fn name_of(&self, mode : &F::Mode) -> &'static str {
+        (self.name_fn)((self.borrow_fn)(mode))
+    }
+
+    fn open_span(&mut self, name : &'static str) {
+        if self.mode_spans {
+            self.span = tracing::span!(target: "mode", Level::DEBUG, "mode", family = type_name::<F>(), name);
+        }
+    }
+
+    pub(crate) fn before_transition(&mut self, outgoing : &F::Mode) {
+        self.outgoing = self.name_of(outgoing);
+    }
+
+    pub(crate) fn after_transition(&mut self, incoming : &F::Mode) {
+        let from = self.outgoing;
+        let to = self.name_of(incoming);
+        let changed = from != to;
+
+        tracing::event!(target: "mode", Level::DEBUG, family = type_name::<F>(), from, to, changed, "transition");
+
+        if changed {
+            self.open_span(to);
+        }
+    }
+}
+
+fn borrow_base<F>(mode : &F::Mode) -> &F::Base
+    where
+        F : Family + ?Sized,
+        F::Mode : Borrow<F::Base>,
+{
+    mode.borrow()
+}
+
+impl<F> Automaton<F>
+    where
+        F : Family + ?Sized,
+        F::Mode : Borrow<F::Base>,
+        F::Base : NamedMode,
+{
+    /// Enables `tracing` support for the `Automaton`, so that every call to one of the `Automaton::next()` family of
+    /// functions emits a `DEBUG` event named `"transition"` with target `"mode"`. Each event has the following fields:
+    /// 
+    /// - `family`: the type name of `F`.
+    /// - `from`: the name of the outgoing `Mode`.
+    /// - `to`: the name of the incoming `Mode`.
+    /// - `changed`: whether the names of the outgoing and incoming `Mode`s differ.
+    /// 
+    /// The name of each `Mode` is returned by [`NamedMode::mode_name()`](trait.NamedMode.html#method.mode_name), so
+    /// `F::Base` must implement [`NamedMode`](trait.NamedMode.html). This requires the `tracing` feature.
+    /// 
+    /// **NOTE:** Since `Automaton` implements `Deref`, this is an associated function rather than a method. Call it as
+    /// `Automaton::trace(&mut automaton)`.
+    /// 
+    /// # Usage
+    /// ```
+    /// use mode::*;
+    /// use std::{fmt, sync::{Arc, Mutex}};
+    /// use tracing::{field::{Field, Visit}, span, Event, Metadata, Subscriber};
+    /// 
+    /// // A Subscriber that writes everything it sees into a Vec, one line per span or event.
+    /// #[derive(Clone, Default)]
+    /// struct Memory { lines : Arc<Mutex<Vec<String>>> }
+    /// 
+    /// struct Line(String);
+    /// impl Visit for Line {
+    ///     fn record_debug(&mut self, field : &Field, value : &dyn fmt::Debug) {
+    ///         self.0.push_str(&format!(" {}={:?}", field.name(), value));
+    ///     }
+    /// }
+    /// 
+    /// impl Subscriber for Memory {
+    ///     fn enabled(&self, _ : &Metadata) -> bool { true }
+    ///     fn new_span(&self, span : &span::Attributes) -> span::Id {
+    ///         let mut line = Line(format!("open {}", span.metadata().name()));
+    ///         span.record(&mut line);
+    ///         let mut lines = self.lines.lock().unwrap();
+    ///         lines.push(line.0);
+    ///         span::Id::from_u64(lines.len() as u64) // Use the line number as the span ID.
+    ///     }
+    ///     fn record(&self, _ : &span::Id, _ : &span::Record) { }
+    ///     fn record_follows_from(&self, _ : &span::Id, _ : &span::Id) { }
+    ///     fn event(&self, event : &Event) {
+    ///         let mut line = Line(String::from("event"));
+    ///         event.record(&mut line);
+    ///         self.lines.lock().unwrap().push(line.0);
+    ///     }
+    ///     fn enter(&self, _ : &span::Id) { }
+    ///     fn exit(&self, _ : &span::Id) { }
+    ///     fn try_close(&self, id : span::Id) -> bool {
+    ///         self.lines.lock().unwrap().push(format!("close {}", id.into_u64()));
+    ///         true
+    ///     }
+    /// }
+    /// 
+    /// struct LightFamily;
+    /// impl Family for LightFamily {
+    ///     type Base = Light;
+    ///     type Mode = Light;
+    /// }
+    /// 
+    /// enum Light { Red, Green }
+    /// impl Mode for Light { type Family = LightFamily; }
+    /// 
+    /// impl NamedMode for Light {
+    ///     fn mode_name(&self) -> &'static str {
+    ///         match self {
+    ///             Light::Red => "Red",
+    ///             Light::Green => "Green",
+    ///         }
+    ///     }
+    /// }
+    /// 
+    /// let memory = Memory::default();
+    /// tracing::subscriber::with_default(memory.clone(), || {
+    ///     let mut automaton = LightFamily::automaton_with_mode(Light::Red);
+    ///     Automaton::trace_with_spans(&mut automaton);
+    ///     Automaton::next(&mut automaton, |_| Light::Green);
+    ///     Automaton::next(&mut automaton, |light| light);
+    /// });
+    /// 
+    /// let family = std::any::type_name::<LightFamily>();
+    /// assert_eq!(*memory.lines.lock().unwrap(), vec![
+    ///     format!("open mode family={:?} name=\"Red\"", family),
+    ///     format!("event message=transition family={:?} from=\"Red\" to=\"Green\" changed=true", family),
+    ///     format!("open mode family={:?} name=\"Green\"", family),
+    ///     "close 1".to_owned(),
+    ///     format!("event message=transition family={:?} from=\"Green\" to=\"Green\" changed=false", family),
+    ///     "close 3".to_owned(),
+    /// ]);
+    /// ```
+    /// 
+    pub fn trace(automaton : &mut Self) {
+        Self::enable_tracing(automaton, false);
+    }
+
+    /// Enables `tracing` support for the `Automaton`, exactly like [`trace()`](struct.Automaton.html#method.trace).
+    /// Additionally, a `DEBUG` span named `"mode"` with target `"mode"` is kept open for as long as each `Mode` is
+    /// current, with the fields `family` and `name`. The span is **not** entered, since the `Automaton` may be updated
+    /// from anywhere, but subscribers will see when it is opened and closed.
+    /// 
+    pub fn trace_with_spans(automaton : &mut Self) {
+        Self::enable_tracing(automaton, true);
+    }
+
+    fn enable_tracing(automaton : &mut Self, mode_spans : bool) {
+        let mut tracer = Tracer {
+            name_fn: <F::Base as NamedMode>::mode_name,
+            borrow_fn: borrow_base::<F>,
+            mode_spans,
+            span: Span::none(),
+            outgoing: "",
+        };
+
+        let current_mode = automaton.mode.as_ref()
+            .expect("Cannot trace the Automaton because it is poisoned!");
+        tracer.open_span(tracer.name_of(current_mode));
+        automaton.tracer = Some(tracer);
+    }
+}
+
+impl<F> Automaton<F>
+    where F : Family + ?Sized
+{
+    /// Disables `tracing` support for the `Automaton`, closing the span for the current `Mode`, if any.
+    /// 
+    pub fn stop_tracing(automaton : &mut Self) {
+        automaton.tracer = None;
+    }
+}
@@ -0,0 +1,224 @@
+// Copyright 2019 Andrew Thomas Christensen
+//
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or http://www.apache.org/licenses/LICENSE-2.0> or the
+// MIT license <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your option. This file may not be copied,
+// modified, or distributed except according to those terms.
+
+/// Describes a single row in a [`TransitionTable`](trait.TransitionTable.html), i.e. an edge in the state graph from
+/// one state to another. All fields other than `from` and `to` are the source text of the corresponding part of the
+/// [`transitions!`](macro.transitions.html) invocation, which is useful for generating diagrams and other tooling.
+/// 
+#[derive(Debug)]
+pub struct Edge<S : 'static> {
+    /// The state this edge leads out of.
+    /// 
+    pub from : S,
+
+    /// The state this edge leads into.
+    /// 
+    pub to : S,
+
+    /// The pattern matched against the input, e.g. `"Input::Coin"`.
+    /// 
+    pub input : &'static str,
+
+    /// The guard expression that must be `true` for this edge to be taken, if any.
+    /// 
+    pub guard : Option<&'static str>,
+
+    /// The action expression evaluated when this edge is taken, if any.
+    /// 
+    pub action : Option<&'static str>,
+}
+
+/// Trait that exposes the states and edges of a state machine over a fieldless `enum` as data. This is implemented for
+/// every table generated by the [`transitions!`](macro.transitions.html) macro, allowing tools (e.g. diagram
+/// generators or reachability checks) to inspect a state machine from the same definition used to run it.
+/// 
+pub trait TransitionTable {
+    /// The `enum` representing all states in the state machine.
+    /// 
+    type State : 'static;
+
+    /// All states in the state machine, in the order they were declared.
+    /// 
+    const STATES : &'static [Self::State];
+
+    /// All edges in the state machine, in the order they are tested against each input.
+    /// 
+    const EDGES : &'static [Edge<Self::State>];
+
+    /// Returns the name of `state`, as declared in the table.
+    /// 
+    fn state_name(state : &Self::State) -> &'static str;
+}
+
+/// Generates a transition table for a state machine whose `Mode` is a fieldless `enum`, along with a transition
+/// function that can be passed into [`Automaton::next_with_result()`](struct.Automaton.html#method.next_with_result).
+/// 
+/// # Syntax
+/// ```text
+/// transitions! {
+///     pub struct TableName for StateEnum {
+///         states { A, B, C }
+///         fn step_fn(input : InputType, context : &mut ContextType, ...);
+/// 
+///         A + InputPattern => B,
+///         B + InputPattern if guard_expression => C / action_expression,
+///         ...
+///     }
+/// }
+/// ```
+/// 
+/// This defines a unit `struct` called `TableName` that implements [`TransitionTable`](trait.TransitionTable.html),
+/// with an associated function called `step_fn()`. The `states` block must list **every** variant of `StateEnum`.
+/// 
+/// The first parameter of `step_fn()` is the input, which is matched against the `InputPattern` of each row in order.
+/// Any parameters after the first are passed through as context, and can be referred to by name from within guard and
+/// action expressions. The generated function has the signature:
+/// 
+/// ```text
+/// fn step_fn(state : StateEnum, input : InputType, context : &mut ContextType, ...) -> (StateEnum, bool)
+/// ```
+/// 
+/// When called, `step_fn()` takes the first row where the current state matches, the input matches `InputPattern`, and
+/// the guard expression (if any) evaluates to `true`. It then evaluates the action expression (if any) and returns the
+/// target state of the row, along with `true`. If no row matches, the current state is returned unchanged, along with
+/// `false`.
+/// 
+/// # Usage
+/// ```
+/// use mode::*;
+/// 
+/// struct TurnstileFamily;
+/// impl Family for TurnstileFamily {
+///     type Base = Turnstile;
+///     type Mode = Turnstile;
+/// }
+/// 
+/// #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+/// enum Turnstile { Locked, Unlocked, Broken }
+/// impl Mode for Turnstile { type Family = TurnstileFamily; }
+/// 
+/// enum Input { Coin, Push, Kick }
+/// 
+/// transitions! {
+///     struct TurnstileTable for Turnstile {
+///         states { Locked, Unlocked, Broken }
+///         fn step(input : Input, coins : &mut u32);
+/// 
+///         Locked + Input::Coin => Unlocked / *coins += 1,
+///         Locked + Input::Kick if *coins > 1 => Broken,
+///         Unlocked + Input::Push => Locked,
+///     }
+/// }
+/// 
+/// let mut coins = 0;
+/// let mut automaton = TurnstileFamily::automaton_with_mode(Turnstile::Locked);
+/// 
+/// assert!(Automaton::next_with_result(&mut automaton, |state| TurnstileTable::step(state, Input::Coin, &mut coins)));
+/// assert_eq!(*automaton, Turnstile::Unlocked);
+/// assert_eq!(coins, 1);
+/// 
+/// // No row matches, so the state is left unchanged.
+/// assert!(!Automaton::next_with_result(&mut automaton, |state| TurnstileTable::step(state, Input::Kick, &mut coins)));
+/// assert_eq!(*automaton, Turnstile::Unlocked);
+/// 
+/// // The guard prevents breaking the turnstile until enough coins have been collected.
+/// Automaton::next_with_result(&mut automaton, |state| TurnstileTable::step(state, Input::Push, &mut coins));
+/// Automaton::next_with_result(&mut automaton, |state| TurnstileTable::step(state, Input::Kick, &mut coins));
+/// assert_eq!(*automaton, Turnstile::Locked);
+/// ```
+/// 
+/// # Inspecting the table
+/// Since the generated `struct` implements `TransitionTable`, the states and edges can also be inspected as data, e.g.
+/// to check that every state can be reached from the initial state:
+/// ```
+/// # use mode::*;
+/// # #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+/// # enum Turnstile { Locked, Unlocked, Broken }
+/// # enum Input { Coin, Push, Kick }
+/// # transitions! {
+/// #     struct TurnstileTable for Turnstile {
+/// #         states { Locked, Unlocked, Broken }
+/// #         fn step(input : Input, coins : &mut u32);
+/// #         Locked + Input::Coin => Unlocked / *coins += 1,
+/// #         Locked + Input::Kick if *coins > 1 => Broken,
+/// #         Unlocked + Input::Push => Locked,
+/// #     }
+/// # }
+/// let edge = &TurnstileTable::EDGES[1];
+/// assert_eq!(TurnstileTable::state_name(&edge.from), "Locked");
+/// assert_eq!(edge.input, "Input::Kick");
+/// assert_eq!(edge.guard, Some("*coins > 1"));
+/// 
+/// let mut reachable = vec![Turnstile::Locked];
+/// while let Some(edge) = TurnstileTable::EDGES.iter()
+///     .find(|edge| reachable.contains(&edge.from) && !reachable.contains(&edge.to))
+/// {
+///     reachable.push(edge.to);
+/// }
+/// assert_eq!(reachable.len(), TurnstileTable::STATES.len());
+/// ```
+/// 
+#[macro_export]
+macro_rules! transitions {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident for $state:ident {
+            states { $($variant:ident),* $(,)? }
+            fn $step:ident($input:ident : $input_type:ty $(, $arg:ident : $arg_type:ty)* $(,)?);
+
+            $($from:ident + $pattern:pat $(if $guard:expr)? => $to:ident $(/ $action:expr)?),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name;
+
+        impl $name {
+            /// Takes the first row of the table that matches the current `state` and `input`, returning the new state
+            /// and `true`, or the current `state` and `false` if no row matches.
+            /// 
+            #[allow(unused_variables)]
+            $vis fn $step(state : $state, $input : $input_type $(, $arg : $arg_type)*) -> ($state, bool) {
+                match (&state, &$input) {
+                    $(
+                        ($state::$from, $pattern) $(if $guard)? => {
+                            $($action;)?
+                            ($state::$to, true)
+                        },
+                    )*
+                    #[allow(unreachable_patterns)]
+                    _ => (state, false),
+                }
+            }
+        }
+
+        impl $crate::TransitionTable for $name {
+            type State = $state;
+
+            const STATES : &'static [$state] = &[$($state::$variant),*];
+
+            const EDGES : &'static [$crate::Edge<$state>] = &[
+                $(
+                    $crate::Edge {
+                        from: $state::$from,
+                        to: $state::$to,
+                        input: stringify!($pattern),
+                        guard: $crate::transitions!(@source $($guard)?),
+                        action: $crate::transitions!(@source $($action)?),
+                    },
+                )*
+            ];
+
+            fn state_name(state : &$state) -> &'static str {
+                match state {
+                    $($state::$variant => stringify!($variant),)*
+                }
+            }
+        }
+    };
+
+    (@source) => { None };
+    (@source $expr:expr) => { Some(stringify!($expr)) };
+}